@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::Value;
+use thomisidae::config::TransportKind;
+
+/// MCP server that fetches web pages as text, extracts links and searches the web.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Config file to use instead of `config.toml` next to the executable
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Load environment variables from this file before the usual `.env` files
+    #[arg(long, global = true, value_name = "PATH")]
+    pub env_file: Option<PathBuf>,
+
+    /// Serve options may also come before the command, as in `thomisidae --transport http`
+    #[command(flatten)]
+    pub serve: ServeArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the MCP server (the default when no command is given)
+    Serve(ServeArgs),
+    /// Print the enabled tools as `tools/list` reports them
+    Tools,
+    /// Validate the config file; exits with status 1 if it has errors
+    CheckConfig,
+    /// Run one tool and print its result, without an MCP client
+    Call(CallArgs),
+}
+
+#[derive(Args, Default)]
+pub struct ServeArgs {
+    /// Transport to serve on (`stdio` or `http`), overriding `[server] transport`
+    #[arg(long)]
+    pub transport: Option<TransportKind>,
+
+    /// Address for the HTTP transport, overriding `[server] bind`
+    #[arg(long, value_name = "HOST:PORT")]
+    pub bind: Option<String>,
+}
+
+#[derive(Args)]
+pub struct CallArgs {
+    /// Tool to run, e.g. `fetch_url_text`
+    pub tool: String,
+
+    /// Tool argument; values that parse as JSON (numbers, booleans, ...) are passed as JSON,
+    /// anything else as a string
+    #[arg(long = "arg", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub args: Vec<(String, Value)>,
+
+    /// All tool arguments as a JSON object; `--arg` values are applied on top
+    #[arg(long, value_name = "OBJECT")]
+    pub json: Option<String>,
+
+    /// What to print
+    #[arg(long, value_enum, default_value_t = Output::Text)]
+    pub output: Output,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Output {
+    /// The text content, as a model would see it
+    Text,
+    /// The structured content (or the whole result for tools without one) as JSON
+    Json,
+}
+
+fn parse_key_value(s: &str) -> Result<(String, Value), String> {
+    let (key, value) = s.split_once('=').ok_or_else(|| format!("expected KEY=VALUE, got '{s}'"))?;
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((key.trim().to_string(), value))
+}
+
+impl ServeArgs {
+    pub fn is_empty(&self) -> bool {
+        self.transport.is_none() && self.bind.is_none()
+    }
+
+    /// Options given after `serve` take precedence over those given before it.
+    pub fn or(self, other: ServeArgs) -> ServeArgs {
+        ServeArgs {
+            transport: self.transport.or(other.transport),
+            bind: self.bind.or(other.bind),
+        }
+    }
+}
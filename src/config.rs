@@ -1,11 +1,29 @@
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 fn default_true() -> bool { true }
 fn default_ttl_secs() -> u64 { 3600 }
 fn default_timeout_ms() -> u64 { 8000 }
+fn default_tool_timeout_ms() -> u64 { 300_000 }
+fn default_max_concurrency() -> usize { 4 }
+fn default_http_bind() -> String { "127.0.0.1:8080".to_string() }
+fn default_http_path() -> String { "/mcp".to_string() }
+fn default_poll_interval_secs() -> u64 { 300 }
+fn default_shutdown_grace_secs() -> u64 { 10 }
+fn default_session_idle_secs() -> u64 { 1800 }
+fn default_cache_max_size_mb() -> u64 { 100 }
+
+/// `$XDG_CACHE_HOME` or `~/.cache` (`%LOCALAPPDATA%` on Windows).
+fn user_cache_dir() -> Option<PathBuf> {
+    let absolute = |var: &str| std::env::var_os(var).map(PathBuf::from).filter(|p| p.is_absolute());
+    if cfg!(windows) {
+        return absolute("LOCALAPPDATA");
+    }
+    absolute("XDG_CACHE_HOME").or_else(|| absolute("HOME").map(|home| home.join(".cache")))
+}
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct RobotsConfig {
@@ -42,13 +60,38 @@ pub struct Config {
     #[serde(default)]
     pub http: HttpConfig,
     #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
     pub allowed_domains: Vec<String>,
     #[serde(default)]
     pub blocked_domains: Vec<String>,
+    #[serde(default)]
+    pub policy_mode: PolicyMode,
+    /// Offer "always allow" when asking, remembered in `approved_domains.toml` next to this file.
+    #[serde(default)]
+    pub persist_domain_approvals: bool,
     #[serde(default = "default_max_response_size")]
     pub max_response_size: usize,
     #[serde(default = "default_timeout_ms")]
     pub timeout_ms: u64,
+    /// Upper bound for a whole tool call, in milliseconds; 0 means no limit.
+    #[serde(default = "default_tool_timeout_ms")]
+    pub tool_timeout_ms: u64,
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub tools: HashMap<String, ToolConfig>,
+    #[serde(default)]
+    pub resources: ResourcesConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub prompts: HashMap<String, PromptConfig>,
+    /// File this config was read from, if any.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
 }
 
 fn default_max_response_size() -> usize { 2 * 1024 * 1024 }
@@ -56,36 +99,44 @@ fn default_max_response_size() -> usize { 2 * 1024 * 1024 }
 impl Config {
     pub fn load_from_path<P: AsRef<Path>>(path: P) -> Self {
         let path = path.as_ref();
-        match fs::read_to_string(path) {
-            Ok(contents) => match toml::from_str::<Config>(&contents) {
-                Ok(cfg) => cfg,
-                Err(err) => {
-                    eprintln!(
-                        "[Thomisidae] Failed to parse config at '{}': {}. Using defaults.",
-                        path.display(),
-                        err
-                    );
-                    Self::default()
-                }
-            },
-            Err(_) => Self::default(),
+        if !path.exists() {
+            return Self::default();
         }
+        Self::try_load_from_path(path).unwrap_or_else(|err| {
+            tracing::warn!("{err}. Using defaults.");
+            Self::default()
+        })
+    }
+
+    /// Like `load_from_path`, but reports read and parse errors instead of falling back to defaults.
+    pub fn try_load_from_path<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read config at '{}': {}", path.display(), err))?;
+        let mut cfg = toml::from_str::<Config>(&contents)
+            .map_err(|err| format!("Failed to parse config at '{}': {}", path.display(), err))?;
+        cfg.source = Some(path.to_path_buf());
+        Ok(cfg)
+    }
+
+    /// `config.toml` next to the executable, if there is one.
+    pub fn default_path() -> Option<PathBuf> {
+        let mut exe_path = std::env::current_exe().ok()?;
+        exe_path.pop();
+        let exe_cfg = exe_path.join("config.toml");
+        exe_cfg.exists().then_some(exe_cfg)
     }
 
     pub fn load_default() -> Self {
-        if let Ok(mut exe_path) = std::env::current_exe() {
-            exe_path.pop();
-            let exe_cfg = exe_path.join("config.toml");
-            if exe_cfg.exists() {
-                eprintln!(
-                    "[Thomisidae] Using config next to executable: {}",
-                    exe_cfg.display()
-                );
-                return Self::load_from_path(exe_cfg);
-            }
+        if let Some(exe_cfg) = Self::default_path() {
+            tracing::info!(
+                "Using config next to executable: {}",
+                exe_cfg.display()
+            );
+            return Self::load_from_path(exe_cfg);
         }
 
-        eprintln!("[Thomisidae] No config.toml found. Using defaults (most features enabled; google_search disabled).");
+        tracing::info!("No config.toml found. Using defaults (most features enabled; google_search disabled).");
         Self::default()
     }
 
@@ -95,6 +146,29 @@ impl Config {
         }
         self.features.get(name).copied().unwrap_or(true)
     }
+
+    /// The HTTP cache directory: `[cache] dir`, or `http_cache` next to the config file,
+    /// or `thomisidae` in the user's cache directory without a config file. `None` when
+    /// there is no config file and no user cache directory is known.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        let base = self.source.as_deref().and_then(Path::parent);
+        match (&self.cache.dir, base) {
+            (Some(dir), Some(base)) => Some(base.join(dir)),
+            (Some(dir), None) => Some(dir.clone()),
+            (None, Some(base)) => Some(base.join("http_cache")),
+            (None, None) => user_cache_dir().map(|dir| dir.join("thomisidae")),
+        }
+    }
+
+    /// Time limit for a call to `tool`, or `None` when it may run without one.
+    pub fn tool_timeout(&self, tool: &str) -> Option<Duration> {
+        let ms = self
+            .tools
+            .get(tool)
+            .and_then(|t| t.timeout_ms)
+            .unwrap_or(self.tool_timeout_ms);
+        (ms > 0).then(|| Duration::from_millis(ms))
+    }
 }
 
 impl Default for Config {
@@ -104,14 +178,36 @@ impl Default for Config {
             google_search: None,
             robots: RobotsConfig::default(),
             http: HttpConfig::default(),
+            network: NetworkConfig::default(),
             allowed_domains: Vec::new(),
             blocked_domains: Vec::new(),
+            policy_mode: PolicyMode::default(),
+            persist_domain_approvals: false,
             max_response_size: default_max_response_size(),
             timeout_ms: default_timeout_ms(),
+            tool_timeout_ms: default_tool_timeout_ms(),
+            max_concurrency: default_max_concurrency(),
+            server: ServerConfig::default(),
+            tools: HashMap::new(),
+            resources: ResourcesConfig::default(),
+            cache: CacheConfig::default(),
+            prompts: HashMap::new(),
+            source: None,
         }
     }
 }
 
+/// What happens to domains outside a non-empty `allowed_domains` list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyMode {
+    /// Refuse them.
+    #[default]
+    Deny,
+    /// Ask the user through MCP elicitation, when the client supports it.
+    Ask,
+}
+
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct HttpConfig {
     #[serde(default)]
@@ -119,3 +215,151 @@ pub struct HttpConfig {
     #[serde(default)]
     pub proxy_url: Option<String>,
 }
+
+/// Which addresses outgoing connections may reach. Only global addresses are allowed
+/// by default; `allow_cidrs` opens private ranges (e.g. an intranet) and `deny_cidrs`
+/// closes ranges even if they are global. Deny wins over allow.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct NetworkConfig {
+    #[serde(default)]
+    pub allow_cidrs: Vec<String>,
+    #[serde(default)]
+    pub deny_cidrs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    #[default]
+    Stdio,
+    Http,
+}
+
+impl std::str::FromStr for TransportKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "stdio" => Ok(Self::Stdio),
+            "http" => Ok(Self::Http),
+            other => Err(format!("Unknown transport '{other}' (expected 'stdio' or 'http')")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerConfig {
+    #[serde(default)]
+    pub transport: TransportKind,
+    #[serde(default = "default_http_bind")]
+    pub bind: String,
+    #[serde(default = "default_http_path")]
+    pub path: String,
+    #[serde(default = "default_true")]
+    pub sse: bool,
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "default_true")]
+    pub watch_config: bool,
+    /// How long in-flight requests may keep running after a shutdown starts.
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// HTTP sessions unused for this long are dropped; 0 keeps them until deleted.
+    #[serde(default = "default_session_idle_secs")]
+    pub session_idle_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            transport: TransportKind::default(),
+            bind: default_http_bind(),
+            path: default_http_path(),
+            sse: true,
+            allowed_origins: Vec::new(),
+            watch_config: true,
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            session_idle_secs: default_session_idle_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResourcesConfig {
+    /// How often subscribed pages are re-checked (minimum 10 seconds).
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+impl Default for ResourcesConfig {
+    fn default() -> Self {
+        Self { poll_interval_secs: default_poll_interval_secs() }
+    }
+}
+
+/// The on-disk HTTP response cache.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Where cached responses are kept; relative paths are resolved against the config
+    /// file's directory. See `Config::cache_dir` for the default.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    /// Upper bound for the cached bodies, in megabytes.
+    #[serde(default = "default_cache_max_size_mb")]
+    pub max_size_mb: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { enabled: true, dir: None, max_size_mb: default_cache_max_size_mb() }
+    }
+}
+
+/// A prompt template configured as `[prompts.<name>]`; `{{argument}}` placeholders
+/// are filled in by `prompts/get`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptConfig {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    pub template: String,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgumentConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptArgumentConfig {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// Per-tool settings, configured as `[tools.<tool_name>]`.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ToolConfig {
+    #[serde(default)]
+    pub annotations: ToolAnnotationsConfig,
+    /// Replaces `tool_timeout_ms` for this tool; 0 means no limit.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Overrides for the MCP annotation hints a tool advertises in `tools/list`.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ToolAnnotationsConfig {
+    #[serde(default)]
+    pub read_only_hint: Option<bool>,
+    #[serde(default)]
+    pub destructive_hint: Option<bool>,
+    #[serde(default)]
+    pub idempotent_hint: Option<bool>,
+    #[serde(default)]
+    pub open_world_hint: Option<bool>,
+}
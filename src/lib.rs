@@ -2,6 +2,7 @@ pub mod server;
 pub mod tools;
 pub mod config;
 pub mod environment;
+pub mod prompts;
 
 pub use tools::{
 	FetchLinksHandler,
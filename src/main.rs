@@ -1,33 +1,159 @@
-use anyhow::Result;
+mod cli;
+
+use anyhow::{Result, anyhow, bail};
+use clap::Parser;
 use thomisidae::server;
 use thomisidae::config::Config;
 use thomisidae::environment;
-use reqwest::Client;
-use std::time::Duration;
+use thomisidae::tools::cache::HttpCache;
+use mcp_protocol_sdk::prelude::Content;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    environment::load_env();
-    let config = Config::load_default();
-    let ua = config
-        .http
-        .user_agent
-        .clone()
-        .or_else(|| config.robots.user_agent.clone())
-        .unwrap_or_else(|| "thomisidae/0.1.0".to_string());
-    let mut builder = Client::builder()
-        .timeout(Duration::from_millis(config.timeout_ms))
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .user_agent(ua);
-
-    if let Some(proxy_url) = &config.http.proxy_url
-        && !proxy_url.trim().is_empty()
+use cli::{CallArgs, Cli, Command, Output, ServeArgs};
+
+fn main() -> Result<ExitCode> {
+    let cli = Cli::parse();
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = runtime.block_on(run(cli));
+    // The stdio transport reads stdin on a blocking thread that cannot be interrupted;
+    // don't wait for it once the server has shut down.
+    runtime.shutdown_background();
+    result
+}
+
+async fn run(cli: Cli) -> Result<ExitCode> {
+    let Cli { config, env_file, serve: serve_args, command } = cli;
+    // dotenv never overrides variables that are already set, so the explicit file wins.
+    if let Some(path) = &env_file
+        && !environment::load_from_path(path)
     {
-        builder = builder.proxy(reqwest::Proxy::all(proxy_url)?);
+        bail!("Failed to load env file '{}'", path.display());
+    }
+    environment::load_env();
+    server::init_logging();
+
+    let command = command.unwrap_or_else(|| Command::Serve(ServeArgs::default()));
+    if !matches!(command, Command::Serve(_)) && !serve_args.is_empty() {
+        bail!("--transport and --bind only apply to the serve command");
+    }
+    match command {
+        Command::Serve(args) => serve(load_config(config.as_deref())?, args.or(serve_args)).await,
+        Command::Tools => {
+            let config = load_config(config.as_deref())?;
+            let cache = Arc::new(HttpCache::disabled());
+            let state = server::build_state(&server::build_client(&config)?, &cache, &config);
+            let tools = serde_json::json!({ "tools": state.tools().tools_meta });
+            println!("{}", serde_json::to_string_pretty(&tools)?);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::CheckConfig => Ok(check_config(config.as_deref())),
+        Command::Call(args) => call(load_config(config.as_deref())?, args).await,
+    }
+}
+
+/// Runs a tool handler the same way `tools/call` does and prints its result. Tool
+/// errors go to stderr with exit status 1.
+async fn call(config: Config, args: CallArgs) -> Result<ExitCode> {
+    let mut arguments: HashMap<String, Value> = match &args.json {
+        Some(json) => serde_json::from_str(json).map_err(|e| anyhow!("--json must be a JSON object: {e}"))?,
+        None => HashMap::new(),
+    };
+    arguments.extend(args.args);
+    let cache = server::open_cache(&config);
+    let state = server::build_state(&server::build_client(&config)?, &cache, &config);
+    let registry = state.tools();
+    let Some(handler) = registry.handlers.get(&args.tool).cloned() else {
+        let mut enabled: Vec<_> = registry.handlers.keys().map(String::as_str).collect();
+        enabled.sort();
+        bail!("Unknown or disabled tool '{}' (enabled: {})", args.tool, enabled.join(", "));
+    };
+    let limit = registry.deadline(&args.tool, &mut arguments).map_err(|e| anyhow!(e))?;
+    let run = handler.call(arguments);
+    let result = match limit {
+        Some(limit) => tokio::time::timeout(limit, run)
+            .await
+            .map_err(|_| anyhow!("{} did not finish within {} ms", args.tool, limit.as_millis()))?,
+        None => run.await,
     }
+    .map_err(|e| anyhow!(e))?;
+    cache.flush().await;
 
-    let client = builder.build()?;
+    let failed = result.is_error == Some(true);
+    let text = result
+        .content
+        .iter()
+        .filter_map(|c| match c {
+            Content::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if failed {
+        eprintln!("{text}");
+        return Ok(ExitCode::FAILURE);
+    }
+    match args.output {
+        Output::Text => println!("{text}"),
+        Output::Json => {
+            let json = match &result.structured_content {
+                Some(structured) => structured.clone(),
+                None => serde_json::to_value(&result)?,
+            };
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn serve(mut config: Config, args: ServeArgs) -> Result<ExitCode> {
+    if let Some(transport) = args.transport {
+        config.server.transport = transport;
+    }
+    if let Some(bind) = args.bind {
+        config.server.bind = bind;
+    }
+    let client = server::build_client(&config)?;
+    let cache = server::open_cache(&config);
+    let server = server::build_server(&client, &cache, &config);
+    let drain = server::run_with_server(server, &config.server).await?;
+    cache.flush().await;
+    Ok(drain.exit_code())
+}
+
+/// An explicit `--config` must exist and parse; otherwise `config.toml` next to the
+/// executable is used if present.
+fn load_config(path: Option<&Path>) -> Result<Config> {
+    match path {
+        Some(path) => Config::try_load_from_path(path).map_err(|err| anyhow!(err)),
+        None => Ok(Config::load_default()),
+    }
+}
 
-    let server = server::build_server(&client, &config);
-    server::run_with_server(server).await
+fn check_config(path: Option<&Path>) -> ExitCode {
+    let path = path.map(Path::to_path_buf).or_else(Config::default_path);
+    let config = match &path {
+        Some(path) => match Config::try_load_from_path(path) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Config::default(),
+    };
+    let problems = server::check_config(&config);
+    let name = path.map_or_else(|| "default config (no config.toml found)".to_string(), |p| p.display().to_string());
+    if problems.is_empty() {
+        println!("{name}: OK");
+        return ExitCode::SUCCESS;
+    }
+    eprintln!("{name}: {} problem(s)", problems.len());
+    for problem in &problems {
+        eprintln!("  - {problem}");
+    }
+    ExitCode::FAILURE
 }
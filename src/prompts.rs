@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::config::Config;
+
+/// A parameterized prompt template served through `prompts/list` and `prompts/get`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Prompt {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub description: String,
+    pub arguments: Vec<PromptArgument>,
+    #[serde(skip)]
+    pub template: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PromptArgument {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+    /// Substituted when an optional argument is not given.
+    #[serde(skip)]
+    pub default: Option<String>,
+}
+
+struct Builtin {
+    name: &'static str,
+    title: &'static str,
+    description: &'static str,
+    /// Tools the template tells the model to use; the prompt is hidden unless all are enabled.
+    requires: &'static [&'static str],
+    /// (name, description, default); arguments without a default are required.
+    arguments: &'static [(&'static str, &'static str, Option<&'static str>)],
+    template: &'static str,
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "research_topic",
+        title: "Research a topic",
+        description: "Search the web for a topic, read the best sources and write a cited answer.",
+        requires: &["google_search", "fetch_url_text"],
+        arguments: &[
+            ("topic", "What to research", None),
+            ("max_sources", "How many results to read (default 3)", Some("3")),
+        ],
+        template: "Research the following topic: {{topic}}\n\n\
+            1. Use the google_search tool to find relevant, authoritative sources. Try another query if the first results are weak.\n\
+            2. Read up to {{max_sources}} of the most relevant results with fetch_url_text (format \"markdown\"). If a page is truncated and the rest matters, call it again with the suggested start_index.\n\
+            3. Write a concise answer that synthesizes the sources, notes where they disagree, and cites every claim with its source URL.",
+    },
+    Builtin {
+        name: "summarize_page",
+        title: "Summarize a page with citations",
+        description: "Fetch a page and summarize it, citing the sections each point comes from.",
+        requires: &["fetch_url_text"],
+        arguments: &[
+            ("url", "Page to summarize", None),
+            ("focus", "What the summary should concentrate on", Some("the main points")),
+        ],
+        template: "Read {{url}} with the fetch_url_text tool (format \"markdown\"). If the result is truncated, keep calling it with the suggested start_index until you have the whole page.\n\n\
+            Summarize the page, focusing on {{focus}}. Only state what the page says, and cite the section or a short quote for each point. \
+            End with the page title and URL as the source.",
+    },
+    Builtin {
+        name: "audit_links",
+        title: "Audit links on a site",
+        description: "List the links on a page and report broken, duplicate or suspicious ones.",
+        requires: &["fetch_page_links"],
+        arguments: &[
+            ("url", "Page whose links should be audited", None),
+            ("same_domain", "Only audit links on the same domain (true/false)", Some("true")),
+        ],
+        template: "Audit the links on {{url}}.\n\n\
+            1. Call fetch_page_links with url={{url}}, same_domain={{same_domain}} and format \"json\".\n\
+            2. Group the links by path prefix and point out duplicates, malformed URLs, plain http links on an https site and tracking parameters.\n\
+            3. If fetch_url_text is available, spot-check a few important links and report any that fail.\n\
+            End with a short list of recommended fixes.",
+    },
+];
+
+/// Built-in prompts whose tools are enabled, plus the `[prompts.<name>]` entries from
+/// the config. A configured prompt replaces a built-in one with the same name.
+pub fn build_prompts(config: &Config) -> Vec<Prompt> {
+    let mut prompts: Vec<Prompt> = BUILTINS
+        .iter()
+        .filter(|b| b.requires.iter().all(|tool| config.is_enabled(tool)))
+        .filter(|b| !config.prompts.contains_key(b.name))
+        .map(|b| Prompt {
+            name: b.name.to_string(),
+            title: Some(b.title.to_string()),
+            description: b.description.to_string(),
+            arguments: b
+                .arguments
+                .iter()
+                .map(|(name, description, default)| PromptArgument {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    required: default.is_none(),
+                    default: default.map(str::to_string),
+                })
+                .collect(),
+            template: b.template.to_string(),
+        })
+        .collect();
+
+    let mut configured: Vec<_> = config.prompts.iter().collect();
+    configured.sort_by_key(|(name, _)| name.as_str());
+    prompts.extend(configured.into_iter().map(|(name, p)| Prompt {
+        name: name.clone(),
+        title: p.title.clone(),
+        description: p.description.clone(),
+        arguments: p
+            .arguments
+            .iter()
+            .map(|a| PromptArgument {
+                name: a.name.clone(),
+                description: a.description.clone(),
+                required: a.required,
+                default: a.default.clone(),
+            })
+            .collect(),
+        template: p.template.clone(),
+    }));
+    prompts
+}
+
+impl Prompt {
+    /// Fills `{{name}}` placeholders for the declared arguments. Placeholders that are
+    /// not declared arguments are left as they are.
+    pub fn render(&self, args: &HashMap<String, String>) -> Result<String, String> {
+        let mut values = HashMap::new();
+        for arg in &self.arguments {
+            let value = args
+                .get(&arg.name)
+                .filter(|v| !v.trim().is_empty())
+                .or(arg.default.as_ref());
+            match value {
+                Some(v) => {
+                    values.insert(arg.name.as_str(), v.as_str());
+                }
+                None if arg.required => return Err(format!("Missing required argument '{}'", arg.name)),
+                None => {
+                    values.insert(arg.name.as_str(), "");
+                }
+            }
+        }
+
+        let mut out = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(open) = rest.find("{{") {
+            out.push_str(&rest[..open]);
+            let after = &rest[open + 2..];
+            match after.find("}}") {
+                Some(close) if values.contains_key(after[..close].trim()) => {
+                    out.push_str(values[after[..close].trim()]);
+                    rest = &after[close + 2..];
+                }
+                _ => {
+                    out.push_str("{{");
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_fills_arguments_and_defaults() {
+        let prompts = build_prompts(&Config::default());
+        assert!(prompts.iter().all(|p| p.name != "research_topic"));
+        let summarize = prompts.iter().find(|p| p.name == "summarize_page").unwrap();
+
+        let args = HashMap::from([("url".to_string(), "https://example.com/".to_string())]);
+        let text = summarize.render(&args).unwrap();
+        assert!(text.starts_with("Read https://example.com/ with"));
+        assert!(text.contains("focusing on the main points."));
+        assert!(summarize.render(&HashMap::new()).is_err());
+    }
+}
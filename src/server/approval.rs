@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tracing::warn;
+
+use super::session::Peer;
+use crate::tools::approval::{ApprovedDomains, Approver};
+
+/// How long a tool call waits for the user to answer an approval prompt.
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// An `Approver` that asks the user of `peer`'s client through `elicitation/create`.
+pub fn approver_for(peer: Peer, approved: Arc<ApprovedDomains>) -> Approver {
+    Approver::new(move |host| {
+        let peer = peer.clone();
+        let approved = approved.clone();
+        async move { ask(&peer, &approved, &host).await }
+    })
+}
+
+async fn ask(peer: &Peer, approved: &ApprovedDomains, host: &str) -> bool {
+    // Saved approvals never get here: `DomainPolicy` already allows those hosts.
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if let Some(allowed) = peer.session.domain_decision(&host) {
+        return allowed;
+    }
+
+    let mut choices = vec![("once", "Allow once"), ("session", "Allow for this session")];
+    if approved.persists() {
+        choices.push(("always", "Always allow"));
+    }
+    choices.push(("never", "Don't allow"));
+    let params = json!({
+        "message": format!("The assistant wants to fetch a page from {host}, which is not in the allowed domains. Allow it?"),
+        "requestedSchema": {
+            "type": "object",
+            "properties": {
+                "decision": {
+                    "type": "string",
+                    "title": format!("Access to {host}"),
+                    "enum": choices.iter().map(|(value, _)| *value).collect::<Vec<_>>(),
+                    "enumNames": choices.iter().map(|(_, name)| *name).collect::<Vec<_>>(),
+                }
+            },
+            "required": ["decision"]
+        }
+    });
+
+    let result = match peer.request("elicitation/create", params, APPROVAL_TIMEOUT).await {
+        Ok(result) => result,
+        Err(err) => {
+            warn!(host, "Could not ask for domain approval: {err}");
+            return false;
+        }
+    };
+    if result.get("action").and_then(Value::as_str) != Some("accept") {
+        return false;
+    }
+    match result.pointer("/content/decision").and_then(Value::as_str) {
+        Some("once") => true,
+        Some("session") => {
+            peer.session.set_domain_decision(&host, true);
+            true
+        }
+        Some("always") => {
+            approved.add(&host);
+            peer.session.set_domain_decision(&host, true);
+            true
+        }
+        Some("never") => {
+            peer.session.set_domain_decision(&host, false);
+            false
+        }
+        _ => false,
+    }
+}
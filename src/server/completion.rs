@@ -0,0 +1,91 @@
+use serde_json::{Value, json};
+
+use crate::tools::ToolMeta;
+
+/// `completion/complete` returns at most this many values.
+const MAX_VALUES: usize = 100;
+
+pub fn completion_result(values: Vec<String>) -> Value {
+    let total = values.len();
+    let values: Vec<_> = values.into_iter().take(MAX_VALUES).collect();
+    json!({
+        "completion": {
+            "values": values,
+            "total": total,
+            "hasMore": total > MAX_VALUES,
+        }
+    })
+}
+
+/// Values of an `enum` property in the tool's input schema that start with `prefix`.
+pub fn enum_values(tool: &ToolMeta, argument: &str, prefix: &str) -> Vec<String> {
+    tool.input_schema
+        .properties
+        .get(argument)
+        .and_then(|p| p.get("enum"))
+        .and_then(Value::as_array)
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|v| starts_with_ignore_case(v, prefix))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn domains(allowed: &[String], prefix: &str) -> Vec<String> {
+    allowed
+        .iter()
+        .filter(|d| starts_with_ignore_case(d, prefix))
+        .cloned()
+        .collect()
+}
+
+/// Completes a URL from pages fetched earlier (most recent first). Both each page's
+/// origin and the full URL are offered, and the scheme may be left out of `prefix`.
+pub fn urls(history: &[String], prefix: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for url in history {
+        let origin = url::Url::parse(url).ok().map(|u| u.origin().ascii_serialization() + "/");
+        for candidate in origin.into_iter().chain(std::iter::once(url.clone())) {
+            let without_scheme = candidate.split_once("://").map_or(candidate.as_str(), |(_, rest)| rest);
+            let matches = starts_with_ignore_case(&candidate, prefix) || starts_with_ignore_case(without_scheme, prefix);
+            if matches && !out.contains(&candidate) {
+                out.push(candidate);
+            }
+        }
+    }
+    out
+}
+
+fn starts_with_ignore_case(value: &str, prefix: &str) -> bool {
+    value.len() >= prefix.len()
+        && value.is_char_boundary(prefix.len())
+        && value[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_complete_hosts_and_paths() {
+        let history = vec![
+            "https://docs.rs/tokio/latest".to_string(),
+            "https://example.com/a".to_string(),
+            "https://docs.rs/serde".to_string(),
+        ];
+        assert_eq!(
+            urls(&history, "docs"),
+            ["https://docs.rs/", "https://docs.rs/tokio/latest", "https://docs.rs/serde"]
+        );
+        assert_eq!(urls(&history, "https://example.com/"), ["https://example.com/", "https://example.com/a"]);
+        assert!(urls(&history, "ftp").is_empty());
+
+        let result = completion_result((0..150).map(|i| i.to_string()).collect());
+        assert_eq!(result["completion"]["values"].as_array().unwrap().len(), MAX_VALUES);
+        assert_eq!(result["completion"]["hasMore"], json!(true));
+    }
+}
@@ -1,5 +1,6 @@
 use jsonrpc_v2::Error as RpcError;
 use mcp_protocol_sdk::McpError;
+use serde_json::{Value, json};
 
 pub trait ToRpcError {
     fn to_rpc_error(self) -> RpcError;
@@ -48,8 +49,12 @@ impl ToRpcError for McpError {
                 message: "Internal error".to_string(),
                 data: Some(Box::new(msg)),
             },
+            McpError::Protocol(msg) => RpcError::Full {
+                code: -32600,
+                message: "Invalid request".to_string(),
+                data: Some(Box::new(msg)),
+            },
             McpError::Transport(msg)
-            | McpError::Protocol(msg)
             | McpError::Serialization(msg)
             | McpError::InvalidUri(msg)
             | McpError::Connection(msg)
@@ -62,3 +67,34 @@ impl ToRpcError for McpError {
         }
     }
 }
+
+/// `McpError::Timeout` as a JSON-RPC error. When the tool recorded partial output before
+/// it was stopped, `data` carries it next to the message.
+pub fn timeout_error(message: String, partial: Option<Value>) -> RpcError {
+    let Some(partial) = partial else {
+        return McpError::Timeout(message).to_rpc_error();
+    };
+    match McpError::Timeout(message.clone()).to_rpc_error() {
+        RpcError::Full { code, message: title, .. } => RpcError::Full {
+            code,
+            message: title,
+            data: Some(Box::new(json!({ "message": message, "partial": partial }))),
+        },
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_error_carries_partial_output() {
+        let err = serde_json::to_value(timeout_error("too slow".into(), Some(json!({ "sections": [] })))).unwrap();
+        assert_eq!(err["code"], json!(-32000));
+        assert_eq!(err["data"]["partial"], json!({ "sections": [] }));
+
+        let err = serde_json::to_value(timeout_error("too slow".into(), None)).unwrap();
+        assert_eq!(err["data"], json!("too slow"));
+    }
+}
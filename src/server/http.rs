@@ -0,0 +1,350 @@
+use anyhow::Result;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use jsonrpc_v2::{MapRouter, Server};
+use serde_json::{Value, json};
+use std::convert::Infallible;
+use std::future::IntoFuture;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use super::io::encode_response;
+use super::protocol;
+use super::session::{Peer, Session, Sessions, is_response};
+use super::shutdown::{Drain, Shutdown};
+use crate::config::ServerConfig;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+/// How often sessions are checked for expiry.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+struct HttpState {
+    server: Arc<Server<MapRouter>>,
+    sessions: Arc<Sessions>,
+    sse: bool,
+    allowed_origins: Arc<Vec<String>>,
+    shutdown: Shutdown,
+    in_flight: InFlight,
+}
+
+/// Number of JSON-RPC requests being handled, so a shutdown that runs out of grace can
+/// report how many it abandoned.
+#[derive(Clone, Default)]
+struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    /// Counts `requests` until the returned guard is dropped.
+    fn start(&self, requests: usize) -> InFlightGuard {
+        self.0.fetch_add(requests, Ordering::SeqCst);
+        InFlightGuard { count: self.0.clone(), requests }
+    }
+
+    fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+struct InFlightGuard {
+    count: Arc<AtomicUsize>,
+    requests: usize,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.count.fetch_sub(self.requests, Ordering::SeqCst);
+    }
+}
+
+/// Serves until `shutdown` is triggered. New connections are then refused, open event
+/// streams are closed, and requests already being handled get `grace` to finish.
+pub async fn run_http(
+    server: Arc<Server<MapRouter>>,
+    cfg: &ServerConfig,
+    shutdown: &Shutdown,
+    grace: Duration,
+) -> Result<Drain> {
+    let state = HttpState {
+        server,
+        sessions: Arc::new(Sessions::default()),
+        sse: cfg.sse,
+        allowed_origins: Arc::new(cfg.allowed_origins.clone()),
+        shutdown: shutdown.clone(),
+        in_flight: InFlight::default(),
+    };
+    let sessions = state.sessions.clone();
+    let in_flight = state.in_flight.clone();
+    if cfg.session_idle_secs > 0 {
+        tokio::spawn(expire_sessions(
+            sessions.clone(),
+            Duration::from_secs(cfg.session_idle_secs),
+            shutdown.clone(),
+        ));
+    }
+
+    let app = Router::new()
+        .route(
+            &cfg.path,
+            post(handle_post).get(handle_get).delete(handle_delete),
+        )
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&cfg.bind).await?;
+    tracing::info!(
+        "Streamable HTTP transport listening on http://{}{}",
+        listener.local_addr()?,
+        cfg.path
+    );
+    let stopping = shutdown.clone();
+    let serve = axum::serve(listener, app).with_graceful_shutdown(async move {
+        stopping.requested().await;
+        sessions.close_all().await;
+    });
+    tokio::select! {
+        served = serve.into_future() => {
+            served?;
+            Ok(Drain::Complete)
+        }
+        _ = async {
+            shutdown.requested().await;
+            tokio::time::sleep(grace).await;
+        } => {
+            let abandoned = in_flight.count();
+            warn!(abandoned, "Shutdown grace period expired");
+            Ok(Drain::TimedOut(abandoned))
+        }
+    }
+}
+
+/// Drops idle sessions until shutdown, so clients that never send DELETE do not pile up.
+async fn expire_sessions(sessions: Arc<Sessions>, idle: Duration, shutdown: Shutdown) {
+    let mut ticks = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = shutdown.requested() => return,
+        }
+        for session in sessions.expire(idle).await {
+            debug!(session = %session.id, "Session expired");
+            session.set_outbound(None);
+        }
+    }
+}
+
+async fn handle_post(State(state): State<HttpState>, headers: HeaderMap, body: Bytes) -> Response {
+    if !origin_allowed(&state, &headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    if state.shutdown.is_requested() {
+        return rpc_error_response(StatusCode::SERVICE_UNAVAILABLE, -32603, "Server is shutting down");
+    }
+
+    let (messages, is_batch) = match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Array(items)) => (items, true),
+        Ok(v @ Value::Object(_)) => (vec![v], false),
+        Ok(_) => return rpc_error_response(StatusCode::BAD_REQUEST, -32600, "Invalid Request"),
+        Err(_) => return rpc_error_response(StatusCode::BAD_REQUEST, -32700, "Parse error"),
+    };
+
+    let is_initialize = messages
+        .iter()
+        .any(|m| m.get("method").and_then(|v| v.as_str()) == Some("initialize"));
+    let requests = messages
+        .iter()
+        .filter(|m| m.get("method").is_some() && m.get("id").is_some())
+        .count();
+
+    if let Some(version) = headers.get(PROTOCOL_VERSION_HEADER).and_then(|v| v.to_str().ok())
+        && !protocol::is_supported(version.trim())
+    {
+        return rpc_error_response(StatusCode::BAD_REQUEST, -32600, "Unsupported MCP-Protocol-Version");
+    }
+
+    // A new session is only registered once its `initialize` has succeeded.
+    let session = if is_initialize {
+        Sessions::create()
+    } else {
+        match lookup_session(&state, &headers).await {
+            Ok(s) => s,
+            Err(resp) => return resp,
+        }
+    };
+
+    // Replies to our own requests (elicitation, sampling) go to whoever is waiting.
+    let (replies, messages): (Vec<_>, Vec<_>) = messages.into_iter().partition(is_response);
+    for reply in &replies {
+        session.resolve(reply);
+    }
+    if messages.is_empty() {
+        return StatusCode::ACCEPTED.into_response();
+    }
+    let body = if is_batch {
+        serde_json::to_vec(&messages)
+    } else {
+        serde_json::to_vec(&messages[0])
+    }
+    .unwrap_or_default();
+
+    if requests == 0 {
+        // Notifications only: process them and acknowledge.
+        let _ = Peer::new(session).scope(state.server.handle(body.as_slice())).await;
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    let guard = state.in_flight.start(requests);
+    let mut send_id = is_initialize;
+    let mut response = if state.sse && accepts_event_stream(&headers) {
+        // Notifications raised while handling go out on this stream ahead of the response;
+        // the stream ends once the response has been sent.
+        let (tx, rx) = mpsc::unbounded_channel();
+        let server = state.server.clone();
+        let peer = Peer::new(session.clone()).with_sink(tx.clone());
+        let sessions = state.sessions.clone();
+        let session = session.clone();
+        // The id goes out before the outcome is known; it is only usable if `initialize` succeeds.
+        tokio::spawn(async move {
+            let _guard = guard;
+            let out = encode_response(peer.scope(server.handle(body.as_slice())).await);
+            if is_initialize {
+                register(&sessions, &session).await;
+            }
+            if let Some(out) = out {
+                let _ = tx.send(out);
+            }
+        });
+        event_stream(rx).into_response()
+    } else {
+        let out = encode_response(Peer::new(session.clone()).scope(state.server.handle(body.as_slice())).await);
+        drop(guard);
+        if is_initialize {
+            send_id = register(&state.sessions, &session).await;
+        }
+        match out {
+            Some(out) => ([(header::CONTENT_TYPE, "application/json")], out).into_response(),
+            None => StatusCode::ACCEPTED.into_response(),
+        }
+    };
+
+    if send_id && let Ok(value) = HeaderValue::from_str(&session.id) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+/// Stores a session created for `initialize` if the client was accepted; returns whether it was.
+async fn register(sessions: &Sessions, session: &Arc<Session>) -> bool {
+    if session.client().is_none() {
+        return false;
+    }
+    sessions.insert(session.clone()).await;
+    true
+}
+
+async fn handle_get(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    if !origin_allowed(&state, &headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    if !accepts_event_stream(&headers) {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+    let session = match lookup_session(&state, &headers).await {
+        Ok(s) => s,
+        Err(resp) => return resp,
+    };
+    // A newer GET stream replaces the previous one for this session.
+    let (tx, rx) = mpsc::unbounded_channel();
+    session.set_outbound(Some(tx));
+    event_stream(rx).into_response()
+}
+
+async fn handle_delete(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    if !origin_allowed(&state, &headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let Some(id) = session_id(&headers) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    match state.sessions.remove(id).await {
+        Some(session) => {
+            session.set_outbound(None);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn lookup_session(state: &HttpState, headers: &HeaderMap) -> Result<Arc<Session>, Response> {
+    let Some(id) = session_id(headers) else {
+        return Err(rpc_error_response(
+            StatusCode::BAD_REQUEST,
+            -32600,
+            "Missing Mcp-Session-Id header",
+        ));
+    };
+    state
+        .sessions
+        .get(id)
+        .await
+        .ok_or_else(|| rpc_error_response(StatusCode::NOT_FOUND, -32001, "Session not found"))
+}
+
+fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(SESSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.to_ascii_lowercase().contains("text/event-stream"))
+}
+
+/// Browser requests from foreign origins are refused to guard against DNS rebinding.
+/// Requests without an Origin header (non-browser clients) are allowed.
+fn origin_allowed(state: &HttpState, headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let host = url::Url::parse(origin)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()));
+    let is_local = matches!(host.as_deref(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    let is_listed = state
+        .allowed_origins
+        .iter()
+        .any(|o| o.trim_end_matches('/').eq_ignore_ascii_case(origin.trim_end_matches('/')));
+    is_local || is_listed
+}
+
+fn event_stream(
+    rx: mpsc::UnboundedReceiver<String>,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, Infallible>>> {
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        let message = rx.recv().await?;
+        Some((Ok(Event::default().event("message").data(message)), rx))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn rpc_error_response(status: StatusCode, code: i64, message: &str) -> Response {
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": code, "message": message },
+    });
+    (status, [(header::CONTENT_TYPE, "application/json")], body.to_string()).into_response()
+}
@@ -1,38 +1,210 @@
 use anyhow::Result;
 use jsonrpc_v2::ResponseObjects;
-use std::io::Write;
-use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::{self, JoinSet};
+use tokio::time::{Duration, Instant};
+use std::collections::HashMap;
 use std::sync::Arc;
+use tracing::{info, warn};
 
 use jsonrpc_v2::Server;
 
+use super::session::{Peer, Session, is_response};
+use super::shutdown::{Drain, Shutdown, shutting_down_response};
+
+/// Reads newline-delimited JSON-RPC messages and dispatches each request on its own task.
+/// Responses are written in completion order by a single writer task, so a slow
+/// tool call never holds up the ones behind it. Notifications are handled inline so
+/// that, e.g., `notifications/initialized` takes effect before the next request, and
+/// responses to our own requests (elicitation, sampling) go straight to their waiter.
+///
+/// When stdin closes or `shutdown` is triggered, new requests are refused and the
+/// in-flight ones get `grace` to finish; stdin is still read in the meantime so that
+/// replies to elicitation and sampling requests can arrive. Requests still running
+/// after that are answered with an error.
 pub async fn run_with_io<R, W>(
     server: Arc<Server<jsonrpc_v2::MapRouter>>,
     reader: R,
-    mut writer: W,
-) -> Result<()>
+    writer: W,
+    shutdown: &Shutdown,
+    grace: Duration,
+) -> Result<Drain>
 where
     R: AsyncBufRead + Unpin,
-    W: Write,
+    W: AsyncWrite + Unpin + Send + 'static,
 {
+    let (tx, rx) = mpsc::unbounded_channel::<String>();
+    let writer_task = tokio::spawn(write_lines(writer, rx));
+
+    let session = Arc::new(Session::new("stdio"));
+    session.set_outbound(Some(tx.clone()));
+    let mut tasks = JoinSet::new();
+    let mut request_ids: HashMap<task::Id, serde_json::Value> = HashMap::new();
     let mut lines = reader.lines();
+    let mut eof = false;
+    let mut draining = false;
+    let deadline = tokio::time::sleep(Duration::MAX);
+    tokio::pin!(deadline);
+
+    let drain = loop {
+        if !draining && (eof || shutdown.is_requested()) {
+            draining = true;
+            info!(in_flight = tasks.len(), "Draining in-flight requests");
+            deadline.as_mut().reset(Instant::now() + grace);
+        }
+        if draining && tasks.is_empty() {
+            break Drain::Complete;
+        }
+        let line = tokio::select! {
+            line = lines.next_line(), if !eof => line?,
+            _ = shutdown.requested(), if !draining => continue,
+            Some(joined) = tasks.join_next_with_id(), if !tasks.is_empty() => {
+                if let Ok((task_id, ())) = joined {
+                    request_ids.remove(&task_id);
+                }
+                continue;
+            }
+            _ = &mut deadline, if draining => {
+                warn!(abandoned = tasks.len(), "Shutdown grace period expired");
+                for id in request_ids.values() {
+                    let _ = tx.send(shutting_down_response(id));
+                }
+                break Drain::TimedOut(tasks.len());
+            }
+        };
+        let Some(line) = line else {
+            eof = true;
+            continue;
+        };
 
-    while let Some(line) = lines.next_line().await? {
         let trimmed = line.trim();
         if trimmed.is_empty() {
             continue;
         }
-        let response = server.handle(trimmed.as_bytes()).await;
-        match response {
-            ResponseObjects::Empty => {}
-            other => {
-                if let Ok(s) = serde_json::to_string(&other) {
-                    let _ = writeln!(writer, "{}", s);
-                    let _ = writer.flush();
-                }
+        let message = serde_json::from_str::<serde_json::Value>(trimmed).ok();
+        if let Some(m) = &message
+            && is_response(m)
+        {
+            session.resolve(m);
+            continue;
+        }
+        let id = match &message {
+            Some(serde_json::Value::Object(m)) => m.get("id").cloned(),
+            _ => None,
+        };
+        if draining {
+            if let Some(id) = id {
+                let _ = tx.send(shutting_down_response(&id));
             }
+            continue;
         }
-    }
 
+        let server = server.clone();
+        let tx = tx.clone();
+        let bytes = trimmed.as_bytes().to_vec();
+        let dispatch = Peer::new(session.clone()).scope(async move {
+            if let Some(out) = encode_response(server.handle(bytes.as_slice()).await) {
+                let _ = tx.send(out);
+            }
+        });
+        match id {
+            None => dispatch.await,
+            Some(id) => {
+                let task = tasks.spawn(dispatch);
+                request_ids.insert(task.id(), id);
+            }
+        }
+    };
+
+    // The writer finishes once every in-flight task has dropped its sender.
+    tasks.shutdown().await;
+    session.set_outbound(None);
+    drop(tx);
+    writer_task.await??;
+    Ok(drain)
+}
+
+async fn write_lines<W>(mut writer: W, mut rx: mpsc::UnboundedReceiver<String>) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(line) = rx.recv().await {
+        writer.write_all(line.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+        writer.flush().await?;
+    }
     Ok(())
 }
+
+pub(super) fn encode_response(response: ResponseObjects) -> Option<String> {
+    match response {
+        ResponseObjects::Empty => None,
+        other => serde_json::to_string(&other).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_v2::{Error as RpcError, Params};
+    use tokio::io::BufReader;
+
+    async fn slow(_: Params<serde_json::Value>) -> Result<&'static str, RpcError> {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        Ok("slow")
+    }
+
+    async fn fast(_: Params<serde_json::Value>) -> Result<&'static str, RpcError> {
+        Ok("fast")
+    }
+
+    #[tokio::test]
+    async fn slow_request_does_not_block_later_ones() {
+        let server = Server::new()
+            .with_method("slow", slow)
+            .with_method("fast", fast)
+            .finish();
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"slow"}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"fast"}"#, "\n",
+        );
+        let (out_w, out_r) = tokio::io::duplex(4096);
+        let drain = run_with_io(server, BufReader::new(input.as_bytes()), out_w, &Shutdown::default(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(drain, Drain::Complete);
+
+        let mut lines = BufReader::new(out_r).lines();
+        let first = lines.next_line().await.unwrap().unwrap();
+        let second = lines.next_line().await.unwrap().unwrap();
+        assert!(first.contains("\"fast\""));
+        assert!(second.contains("\"slow\""));
+    }
+
+    #[tokio::test]
+    async fn shutdown_finishes_in_flight_and_refuses_new_requests() {
+        let server = Server::new().with_method("slow", slow).finish();
+        let (mut in_w, in_r) = tokio::io::duplex(4096);
+        let (out_w, out_r) = tokio::io::duplex(4096);
+        let shutdown = Shutdown::default();
+        let run = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { run_with_io(server, BufReader::new(in_r), out_w, &shutdown, Duration::from_secs(5)).await }
+        });
+
+        in_w.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"slow\"}\n").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown.trigger();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        in_w.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"slow\"}\n").await.unwrap();
+
+        // stdin stays open: the loop ends once the in-flight request is done.
+        assert_eq!(run.await.unwrap().unwrap(), Drain::Complete);
+        let mut lines = BufReader::new(out_r).lines();
+        let refused = lines.next_line().await.unwrap().unwrap();
+        let finished = lines.next_line().await.unwrap().unwrap();
+        assert!(refused.contains("\"id\":2") && refused.contains("shutting down"));
+        assert!(finished.contains("\"slow\""));
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use super::session::Peer;
+
+/// Syslog severities used by the MCP `logging` capability, least severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl From<&Level> for LogLevel {
+    fn from(level: &Level) -> Self {
+        match *level {
+            Level::TRACE | Level::DEBUG => LogLevel::Debug,
+            Level::INFO => LogLevel::Info,
+            Level::WARN => LogLevel::Warning,
+            Level::ERROR => LogLevel::Error,
+        }
+    }
+}
+
+/// Installs the global subscriber: human-readable lines on stderr (level from
+/// `THOMISIDAE_LOG`, default `info`) plus forwarding to MCP clients that called
+/// `logging/setLevel`. Only this crate's events are emitted.
+pub fn init_logging() {
+    let stderr_level = std::env::var("THOMISIDAE_LOG")
+        .ok()
+        .and_then(|v| v.parse::<LevelFilter>().ok())
+        .unwrap_or(LevelFilter::INFO);
+    let crate_name = env!("CARGO_CRATE_NAME");
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .with_filter(Targets::new().with_target(crate_name, stderr_level));
+    let mcp = McpLogLayer.with_filter(Targets::new().with_target(crate_name, LevelFilter::DEBUG));
+    let _ = tracing_subscriber::registry().with(stderr).with(mcp).try_init();
+}
+
+/// Sends events raised while handling a request to that request's client as
+/// `notifications/message`, filtered by the level the session asked for.
+struct McpLogLayer;
+
+impl<S: Subscriber> Layer<S> for McpLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let Some(peer) = Peer::current() else {
+            return;
+        };
+        let metadata = event.metadata();
+        let level = LogLevel::from(metadata.level());
+        if peer.session.log_level().is_none_or(|min| level < min) {
+            return;
+        }
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        peer.notify(
+            "notifications/message",
+            json!({ "level": level, "logger": metadata.target(), "data": fields.into_data() }),
+        );
+    }
+}
+
+#[derive(Default)]
+struct JsonFields(Map<String, Value>);
+
+impl JsonFields {
+    /// A bare message is sent as a string; structured fields make it an object.
+    fn into_data(mut self) -> Value {
+        if self.0.len() == 1
+            && let Some(message) = self.0.remove("message")
+        {
+            return message;
+        }
+        Value::Object(self.0)
+    }
+}
+
+impl Visit for JsonFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), json!(format!("{value:?}")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_order_by_severity() {
+        assert!(LogLevel::Warning > LogLevel::Info);
+        assert_eq!(LogLevel::from(&Level::TRACE), LogLevel::Debug);
+        let parsed: LogLevel = serde_json::from_value(json!("critical")).unwrap();
+        assert_eq!(parsed, LogLevel::Critical);
+    }
+}
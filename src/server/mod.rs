@@ -1,8 +1,21 @@
+mod approval;
+mod completion;
 mod error;
+mod http;
+mod logging;
+mod protocol;
+mod reload;
 mod rpc;
 mod setup;
+mod session;
+mod shutdown;
 mod state;
+mod subscriptions;
 mod transport;
 mod io;
 
+pub use logging::init_logging;
+pub use setup::{TOOL_NAMES, build_client, build_state, check_config, open_cache};
+pub use shutdown::Drain;
+pub use state::AppState;
 pub use transport::{build_server, run_with_server};
@@ -0,0 +1,41 @@
+/// Protocol revisions this server can speak, newest first.
+pub const SUPPORTED_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+pub fn is_supported(version: &str) -> bool {
+    SUPPORTED_VERSIONS.contains(&version)
+}
+
+/// Picks the version to answer `initialize` with: the requested one if supported,
+/// otherwise the newest supported revision older than it. Revisions are dates, so
+/// a client asking for a newer spec gets our latest. Returns `None` when the request
+/// predates every supported revision or is not a revision date at all.
+pub fn negotiate(requested: &str) -> Option<&'static str> {
+    if !is_revision_date(requested) {
+        return None;
+    }
+    SUPPORTED_VERSIONS.iter().copied().find(|v| *v <= requested)
+}
+
+fn is_revision_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 10
+        && b.iter().enumerate().all(|(i, c)| match i {
+            4 | 7 => *c == b'-',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_picks_supported_or_older_version() {
+        assert_eq!(negotiate("2025-06-18"), Some("2025-06-18"));
+        assert_eq!(negotiate("2025-03-26"), Some("2025-03-26"));
+        assert_eq!(negotiate("2026-01-01"), Some(SUPPORTED_VERSIONS[0]));
+        assert_eq!(negotiate("2025-05-01"), Some("2025-03-26"));
+        assert_eq!(negotiate("2024-01-01"), None);
+        assert_eq!(negotiate("latest"), None);
+    }
+}
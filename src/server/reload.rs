@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde_json::json;
+use tracing::{info, warn};
+
+use super::setup::build_tools;
+use super::state::AppState;
+use crate::config::Config;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polls the config file and rebuilds the tools (including domain policy and robots
+/// settings) and prompts when it changes. Clients are told through
+/// `notifications/tools/list_changed` / `notifications/prompts/list_changed` whenever
+/// the advertised lists differ. Network settings (timeout, proxy,
+/// user agent), concurrency and transport still require a restart.
+pub fn spawn_config_watcher(state: Arc<AppState>, path: PathBuf) {
+    tokio::spawn(async move {
+        let mut last_modified = modified(&path);
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let current = modified(&path);
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+            match Config::try_load_from_path(&path) {
+                Ok(config) => {
+                    reload(&state, &config);
+                    info!("Reloaded config from {}", path.display());
+                }
+                Err(err) => warn!("{err}. Keeping previous config."),
+            }
+        }
+    });
+}
+
+pub fn reload(state: &AppState, config: &Config) {
+    let tools = build_tools(&state.client, &state.pages, &state.cache, config);
+    let new_list = serde_json::to_value(&tools.tools_meta).ok();
+    let new_prompts = serde_json::to_value(&tools.prompts).ok();
+    let previous = state.replace_tools(tools);
+    if serde_json::to_value(&previous.tools_meta).ok() != new_list {
+        state.peers.broadcast("notifications/tools/list_changed", json!({}));
+    }
+    if serde_json::to_value(&previous.prompts).ok() != new_prompts {
+        state.peers.broadcast("notifications/prompts/list_changed", json!({}));
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -1,36 +1,111 @@
 use anyhow::Result;
-use jsonrpc_v2::{Data, Error as RpcError, Params};
+use futures_util::future::Abortable;
+use jsonrpc_v2::{Data, Error as RpcError, Id, Params};
+use mcp_protocol_sdk::McpError;
+use mcp_protocol_sdk::prelude::Content;
 use serde_json::json;
 use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::{debug, info};
 
-use super::state::{AppState, CallParams};
-use crate::server::error::ToRpcError;
+use super::approval;
+use super::completion;
+use super::protocol;
+use super::session::{ClientInfo, Peer, Session, request_key};
+use super::subscriptions;
+use super::state::{
+    AppState, CallParams, CancelledParams, CompleteParams, CompletionRef, GetPromptParams, InitializeParams,
+    ResourceParams, SetLevelParams,
+};
+use crate::server::error::{ToRpcError, timeout_error};
+use crate::tools::cache::CacheMode;
+use crate::tools::pages::{RESOURCE_SCHEME, StoredPage, url_from_uri};
+use crate::tools::partial::Partial;
+use crate::tools::progress::Progress;
+use crate::tools::sampling::Sampler;
 
 pub async fn initialize(
-    _: Params<serde_json::Value>,
-    _data: Data<AppState>,
+    params: Params<InitializeParams>,
+    data: Data<AppState>,
 ) -> Result<serde_json::Value, RpcError> {
+    let InitializeParams { protocol_version, client_info, capabilities } = params.0;
+    let Some(version) = protocol::negotiate(&protocol_version) else {
+        return Err(RpcError::Full {
+            code: -32602,
+            message: "Unsupported protocol version".to_string(),
+            data: Some(Box::new(json!({
+                "supported": protocol::SUPPORTED_VERSIONS,
+                "requested": protocol_version,
+            }))),
+        });
+    };
+
+    if let Some(session) = Session::current() {
+        session.set_client(ClientInfo {
+            protocol_version: version.to_string(),
+            info: client_info,
+            capabilities,
+        });
+        data.peers.register(&session);
+    }
     Ok(json!({
-        "protocolVersion": "2025-06-18",
-        "serverInfo": { "name": "url-fetcher", "version": "0.1.0" },
-        "capabilities": { "tools": { "listChanged": false } }
+        "protocolVersion": version,
+        "serverInfo": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+        "capabilities": {
+            "tools": { "listChanged": true },
+            "resources": { "subscribe": true },
+            "prompts": { "listChanged": true },
+            "completions": {},
+            "logging": {}
+        }
     }))
 }
 
+pub async fn initialized(_: Params<serde_json::Value>) -> Result<serde_json::Value, RpcError> {
+    if let Some(session) = Session::current() {
+        session.mark_initialized();
+        if let Some(client) = session.client() {
+            let name = client.info.get("name").and_then(|v| v.as_str()).unwrap_or("unknown client");
+            let version = client.info.get("version").and_then(|v| v.as_str()).unwrap_or("");
+            info!(
+                "{name} {version} initialized (protocol {}, session {})",
+                client.protocol_version, session.id
+            );
+        }
+    }
+    Ok(serde_json::Value::Null)
+}
+
+/// Tools that only work through `sampling/createMessage`.
+const SAMPLING_TOOLS: &[&str] = &["summarize_url"];
+
+/// Lists the enabled tools, leaving out those the client cannot use.
 pub async fn tools_list(
     _: Params<serde_json::Value>,
     data: Data<AppState>,
 ) -> Result<serde_json::Value, RpcError> {
-    Ok(json!({ "tools": &data.tools_meta.0 }))
+    let sampling = client_supports("sampling");
+    let tools = data.tools();
+    let visible: Vec<_> = tools
+        .tools_meta
+        .0
+        .iter()
+        .filter(|t| sampling || !SAMPLING_TOOLS.contains(&t.name.as_str()))
+        .collect();
+    Ok(json!({ "tools": visible }))
 }
 
 pub async fn tools_call(
     params: Params<CallParams>,
     data: Data<AppState>,
+    id: Option<Id>,
 ) -> Result<serde_json::Value, RpcError> {
-    let CallParams { name, arguments } = params.0;
+    ensure_initialized()?;
+
+    let CallParams { name, arguments, meta } = params.0;
     if name.is_empty() {
-        return Err(RpcError::internal("Missing 'name' in params"));
+        return Err(McpError::validation("Missing 'name' in params").to_rpc_error());
     }
 
     let arguments = if arguments.is_null() {
@@ -38,16 +113,337 @@ pub async fn tools_call(
     } else {
         arguments
     };
-    let arg_map: HashMap<String, serde_json::Value> = serde_json::from_value(arguments)
-        .map_err(|e| RpcError::internal(format!("Invalid 'arguments': {e}")))?;
+    let mut arg_map: HashMap<String, serde_json::Value> = serde_json::from_value(arguments)
+        .map_err(|e| McpError::validation(format!("Invalid 'arguments': {e}")).to_rpc_error())?;
+    let progress = meta
+        .as_ref()
+        .and_then(|m| m.get("progressToken"))
+        .cloned()
+        .zip(Peer::current())
+        .map(|(token, peer)| {
+            Progress::new(token, move |params| {
+                peer.notify("notifications/progress", params);
+            })
+        });
+
+    let url_arg = arg_map.get("url").and_then(|v| v.as_str()).map(str::to_string);
+
+    let registry = data.tools();
+    let limit = registry.deadline(&name, &mut arg_map).map_err(|e| e.to_rpc_error())?;
+    if let Some(handler) = registry.handlers.get(&name).cloned() {
+        debug!(tool = %name, "Calling tool");
+        let call = async {
+            let _permit = data.concurrency.acquire().await.map_err(|e| RpcError::internal(format!("Semaphore error: {e}")))?;
+            let partial = Partial::default();
+            let run = partial.clone().scope(with_client_features(&data, handler.call(arg_map)));
+            let run = async {
+                match progress {
+                    Some(p) => p.scope(run).await,
+                    None => run.await,
+                }
+            };
+            let result = match limit {
+                Some(limit) => match tokio::time::timeout(limit, run).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        info!(tool = %name, limit_ms = limit.as_millis() as u64, "Tool call timed out");
+                        let message = format!("Tool '{name}' did not finish within {} ms", limit.as_millis());
+                        return Err(timeout_error(message, partial.take()));
+                    }
+                },
+                None => run.await,
+            };
+            match result {
+                Ok(tr) => {
+                    if tr.is_error != Some(true)
+                        && let (Some(session), Some(url)) = (Session::current(), &url_arg)
+                    {
+                        session.remember_url(url);
+                    }
+                    Ok(serde_json::to_value(tr).unwrap_or(json!(null)))
+                }
+                Err(e) => Err(e.to_rpc_error()),
+            }
+        };
+
+        let (Some(session), Some(id)) = (Session::current(), id) else {
+            return call.await;
+        };
+        // Dropping the aborted future also drops any response stream still being read.
+        let key = request_key(&id);
+        let (registration, _in_flight) = session.track(key.clone());
+        match Abortable::new(call, registration).await {
+            Ok(res) => res,
+            Err(_) => {
+                info!(tool = %name, request = %key, "Tool call cancelled by the client");
+                Err(McpError::Cancelled(format!("Request {key} was cancelled by the client")).to_rpc_error())
+            }
+        }
+    } else {
+        Err(McpError::validation(format!("Unknown tool: {name}")).to_rpc_error())
+    }
+}
+
+pub async fn prompts_list(
+    _: Params<serde_json::Value>,
+    data: Data<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    Ok(json!({ "prompts": &data.tools().prompts }))
+}
+
+pub async fn prompts_get(
+    params: Params<GetPromptParams>,
+    data: Data<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    let GetPromptParams { name, arguments } = params.0;
+    let tools = data.tools();
+    let prompt = tools
+        .prompts
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| McpError::PromptNotFound(format!("Unknown prompt: {name}")).to_rpc_error())?;
+    let text = prompt
+        .render(&arguments)
+        .map_err(|e| McpError::validation(e).to_rpc_error())?;
+    Ok(json!({
+        "description": prompt.description,
+        "messages": [{ "role": "user", "content": { "type": "text", "text": text } }]
+    }))
+}
+
+/// Completes URL arguments from this session's history, `site` from the domain allow
+/// list, and enum arguments of tools from their input schema.
+pub async fn completion_complete(
+    params: Params<CompleteParams>,
+    data: Data<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    let CompleteParams { reference, argument } = params.0;
+    let prefix = argument.value.as_str();
+    let tools = data.tools();
+    let arg = argument.name.as_str();
+    let by_name = || match arg {
+        "url" => {
+            let history = Session::current().map(|s| s.history()).unwrap_or_default();
+            completion::urls(&history, prefix)
+        }
+        "site" => completion::domains(tools.fetcher.policy().allowed_domains(), prefix),
+        _ => Vec::new(),
+    };
+    let values = match &reference {
+        CompletionRef::Tool { name } => match tools.tools_meta.0.iter().find(|t| t.name == *name) {
+            Some(_) if matches!(arg, "url" | "site") => by_name(),
+            Some(tool) => completion::enum_values(tool, arg, prefix),
+            None => Vec::new(),
+        },
+        CompletionRef::Prompt { name } => {
+            let declared = tools
+                .prompts
+                .iter()
+                .any(|p| p.name == *name && p.arguments.iter().any(|a| a.name == arg));
+            if declared { by_name() } else { Vec::new() }
+        }
+        CompletionRef::Resource { uri } if uri.starts_with(RESOURCE_SCHEME) => by_name(),
+        CompletionRef::Resource { .. } => Vec::new(),
+    };
+    Ok(completion::completion_result(values))
+}
+
+pub async fn resources_list(
+    _: Params<serde_json::Value>,
+    data: Data<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    let history = Session::current().map(|s| s.history()).unwrap_or_default();
+    let resources: Vec<_> = data
+        .pages
+        .list()
+        .iter()
+        .filter(|page| history.contains(&page.url))
+        .map(|page| {
+            json!({
+                "uri": page.uri(),
+                "name": page.url,
+                "title": page.title,
+                "mimeType": page.mime_type,
+                "size": page.text.len(),
+            })
+        })
+        .collect();
+    Ok(json!({ "resources": resources }))
+}
+
+pub async fn resources_templates_list(_: Params<serde_json::Value>) -> Result<serde_json::Value, RpcError> {
+    Ok(json!({
+        "resourceTemplates": [{
+            "uriTemplate": format!("{RESOURCE_SCHEME}{{url}}"),
+            "name": "web_page",
+            "title": "Web page",
+            "description": "Extracted text of an http(s) page, fetched with fetch_url_text defaults (domain policy and robots.txt apply). Example: web://https://example.com/",
+            "mimeType": "text/plain",
+        }]
+    }))
+}
+
+/// Returns a stored page, fetching it through `fetch_url_text` the first time this
+/// session reads it.
+pub async fn resources_read(
+    params: Params<ResourceParams>,
+    data: Data<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    ensure_initialized()?;
+    let uri = params.0.uri;
+    let not_found = || McpError::ResourceNotFound(format!("Unknown resource: {uri}")).to_rpc_error();
+    let url = url_from_uri(&uri).ok_or_else(not_found)?;
+
+    let page = match own_page(&data, url) {
+        Some(page) => page,
+        None => {
+            fetch_page(&data, url, CacheMode::Default).await?;
+            if let Some(session) = Session::current() {
+                session.remember_url(url);
+            }
+            data.pages.get(url).ok_or_else(not_found)?
+        }
+    };
+    Ok(json!({ "contents": [resource_contents(&page)] }))
+}
 
-    if let Some(handler) = data.handlers.get(&name) {
-        let _permit = data.concurrency.acquire().await.map_err(|e| RpcError::internal(format!("Semaphore error: {e}")))?;
-        match handler.call(arg_map).await {
-            Ok(tr) => Ok(serde_json::to_value(tr).unwrap_or(json!(null))),
-            Err(e) => Err(e.to_rpc_error()),
+/// A stored page, if this session fetched it. The store is shared by all sessions, and
+/// on a shared HTTP server one user must not read the pages another user fetched.
+fn own_page(data: &AppState, url: &str) -> Option<StoredPage> {
+    let session = Session::current()?;
+    session.history().iter().any(|u| u == url).then(|| data.pages.get(url)).flatten()
+}
+
+/// Watches a `web://` page for changes; subscribers get `notifications/resources/updated`.
+pub async fn resources_subscribe(
+    params: Params<ResourceParams>,
+    data: Data<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    ensure_initialized()?;
+    let uri = params.0.uri;
+    let url = url_from_uri(&uri)
+        .ok_or_else(|| McpError::ResourceNotFound(format!("Unknown resource: {uri}")).to_rpc_error())?
+        .to_string();
+    // Refuse what the poller would never be allowed to fetch instead of watching it forever.
+    data.tools().fetcher.ensure_permitted(&url).await.map_err(|e| e.to_rpc_error())?;
+    if let Some(session) = Session::current()
+        && data.subscriptions.subscribe(&url, &session)
+    {
+        // Record the current version now so changes before the first poll are noticed.
+        let state = data.0.clone();
+        tokio::spawn(async move { subscriptions::check(&state, &url).await });
+    }
+    Ok(json!({}))
+}
+
+pub async fn resources_unsubscribe(
+    params: Params<ResourceParams>,
+    data: Data<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    if let (Some(session), Some(url)) = (Session::current(), url_from_uri(&params.0.uri)) {
+        data.subscriptions.unsubscribe(url, &session);
+    }
+    Ok(json!({}))
+}
+
+/// Runs `fetch_url_text` on `url`, which stores the extracted page.
+pub(super) async fn fetch_page(data: &AppState, url: &str, cache: CacheMode) -> Result<(), RpcError> {
+    let Some(handler) = data.tools().handlers.get("fetch_url_text").cloned() else {
+        return Err(McpError::ResourceNotFound(format!(
+            "{url} has not been fetched and fetch_url_text is disabled"
+        ))
+        .to_rpc_error());
+    };
+    debug!(url, "Fetching page for resources/read");
+    let _permit = data.concurrency.acquire().await.map_err(|e| RpcError::internal(format!("Semaphore error: {e}")))?;
+    let arguments = HashMap::from([("url".to_string(), json!(url)), ("cache".to_string(), json!(cache.as_str()))]);
+    let result = with_client_features(data, handler.call(arguments)).await.map_err(|e| e.to_rpc_error())?;
+    if result.is_error == Some(true) {
+        let message = result
+            .content
+            .iter()
+            .find_map(|c| match c {
+                Content::Text { text, .. } => Some(text.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| format!("Failed to fetch {url}"));
+        return Err(McpError::internal(message).to_rpc_error());
+    }
+    Ok(())
+}
+
+fn resource_contents(page: &StoredPage) -> serde_json::Value {
+    let fetched_at = page.fetched_at.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    json!({
+        "uri": page.uri(),
+        "mimeType": page.mime_type,
+        "text": page.text,
+        "_meta": {
+            "url": page.url,
+            "finalUrl": page.final_url,
+            "status": page.status,
+            "title": page.title,
+            "contentType": page.content_type,
+            "fetchedAt": fetched_at,
+        },
+    })
+}
+
+/// How long a tool waits for the client's model to answer `sampling/createMessage`.
+const SAMPLING_TIMEOUT: Duration = Duration::from_secs(120);
+
+fn client_supports(capability: &str) -> bool {
+    Session::current()
+        .and_then(|s| s.client())
+        .is_some_and(|c| c.supports(capability))
+}
+
+/// Runs a tool with access to what the client offers: asking the user about unlisted
+/// domains (elicitation) and the client's model (sampling).
+async fn with_client_features<F: Future>(data: &AppState, fut: F) -> F::Output {
+    let Some(peer) = Peer::current() else {
+        return fut.await;
+    };
+    let sampler = client_supports("sampling").then(|| {
+        let peer = peer.clone();
+        Sampler::new(move |params| {
+            let peer = peer.clone();
+            async move { peer.request("sampling/createMessage", params, SAMPLING_TIMEOUT).await }
+        })
+    });
+    let fut = async move {
+        match sampler {
+            Some(sampler) => sampler.scope(fut).await,
+            None => fut.await,
         }
+    };
+    if client_supports("elicitation") {
+        approval::approver_for(peer, data.tools().fetcher.policy().approved().clone()).scope(fut).await
     } else {
-        Err(RpcError::internal("Tool not found"))
+        fut.await
+    }
+}
+
+/// Requests that do work on the client's behalf wait for `notifications/initialized`.
+fn ensure_initialized() -> Result<(), RpcError> {
+    if let Some(session) = Session::current()
+        && !session.is_initialized()
+    {
+        return Err(McpError::protocol("Session not initialized: send 'initialize' and 'notifications/initialized' first").to_rpc_error());
+    }
+    Ok(())
+}
+
+pub async fn cancelled(params: Params<CancelledParams>) -> Result<serde_json::Value, RpcError> {
+    if let Some(session) = Session::current() {
+        session.cancel(&request_key(&params.0.request_id));
+    }
+    Ok(serde_json::Value::Null)
+}
+
+pub async fn set_log_level(params: Params<SetLevelParams>) -> Result<serde_json::Value, RpcError> {
+    if let Some(session) = Session::current() {
+        session.set_log_level(params.0.level);
     }
+    Ok(json!({}))
 }
@@ -0,0 +1,381 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use futures_util::future::{AbortHandle, AbortRegistration};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tokio::sync::RwLock;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+
+use super::logging::LogLevel;
+
+tokio::task_local! {
+    static CURRENT: Peer;
+}
+
+/// What the client declared in its `initialize` request.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub protocol_version: String,
+    pub info: Value,
+    pub capabilities: Value,
+}
+
+impl ClientInfo {
+    /// Whether the client declared `capability` (e.g. `"elicitation"`) in `initialize`.
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.get(capability).is_some_and(|v| !v.is_null())
+    }
+}
+
+/// Outcome of a request the server sent to the client: its `result`, or its `error` object.
+type Reply = Result<Value, Value>;
+
+/// Per-connection state: one for the stdio transport, one per `Mcp-Session-Id` over HTTP.
+#[derive(Debug)]
+pub struct Session {
+    pub id: String,
+    in_flight: Mutex<HashMap<String, AbortHandle>>,
+    outbound: Mutex<Option<UnboundedSender<String>>>,
+    client: Mutex<Option<ClientInfo>>,
+    initialized: AtomicBool,
+    log_level: Mutex<Option<LogLevel>>,
+    history: Mutex<VecDeque<String>>,
+    domain_decisions: Mutex<HashMap<String, bool>>,
+    pending: Mutex<HashMap<String, oneshot::Sender<Reply>>>,
+    next_request_id: AtomicU64,
+    last_active: Mutex<Instant>,
+}
+
+/// How many fetched URLs a session remembers for completions.
+const HISTORY_LEN: usize = 200;
+
+impl Session {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            in_flight: Mutex::new(HashMap::new()),
+            outbound: Mutex::new(None),
+            client: Mutex::new(None),
+            initialized: AtomicBool::new(false),
+            log_level: Mutex::new(None),
+            history: Mutex::new(VecDeque::new()),
+            domain_decisions: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            next_request_id: AtomicU64::new(1),
+            last_active: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn set_client(&self, client: ClientInfo) {
+        *self.client.lock().unwrap() = Some(client);
+    }
+
+    pub fn client(&self) -> Option<ClientInfo> {
+        self.client.lock().unwrap().clone()
+    }
+
+    /// Called on `notifications/initialized`, after a successful `initialize`.
+    pub fn mark_initialized(&self) {
+        if self.client.lock().unwrap().is_some() {
+            self.initialized.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    /// Minimum level forwarded as `notifications/message`; nothing is sent until
+    /// the client calls `logging/setLevel`.
+    pub fn log_level(&self) -> Option<LogLevel> {
+        *self.log_level.lock().unwrap()
+    }
+
+    pub fn set_log_level(&self, level: LogLevel) {
+        *self.log_level.lock().unwrap() = Some(level);
+    }
+
+    /// Marks the session as used now, postponing its expiry.
+    pub fn touch(&self) {
+        *self.last_active.lock().unwrap() = Instant::now();
+    }
+
+    /// Unused for at least `max`, with no request running and no event stream open.
+    fn is_idle(&self, max: Duration) -> bool {
+        let listening = self.outbound.lock().unwrap().as_ref().is_some_and(|tx| !tx.is_closed());
+        !listening && self.in_flight.lock().unwrap().is_empty() && self.last_active.lock().unwrap().elapsed() >= max
+    }
+
+    /// Records a URL fetched for this client; used to complete URL arguments.
+    pub fn remember_url(&self, url: &str) {
+        let mut history = self.history.lock().unwrap();
+        history.retain(|u| u != url);
+        history.push_front(url.to_string());
+        history.truncate(HISTORY_LEN);
+    }
+
+    /// Fetched URLs, most recent first.
+    pub fn history(&self) -> Vec<String> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// The user's "for this session" / "never" answer for a domain, if any.
+    pub fn domain_decision(&self, host: &str) -> Option<bool> {
+        self.domain_decisions.lock().unwrap().get(host).copied()
+    }
+
+    pub fn set_domain_decision(&self, host: &str, allowed: bool) {
+        self.domain_decisions.lock().unwrap().insert(host.to_string(), allowed);
+    }
+
+    /// Hands a JSON-RPC response from the client to the server request waiting for it.
+    pub fn resolve(&self, response: &Value) -> bool {
+        let Some(id) = response.get("id") else {
+            return false;
+        };
+        let Some(tx) = self.pending.lock().unwrap().remove(&request_key(id)) else {
+            return false;
+        };
+        let reply = match response.get("error") {
+            Some(error) => Err(error.clone()),
+            None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+        };
+        tx.send(reply).is_ok()
+    }
+
+    /// Session of the request currently being handled, if any.
+    pub fn current() -> Option<Arc<Session>> {
+        Peer::current().map(|p| p.session)
+    }
+
+    /// Sets the channel used for messages that are not tied to a request stream
+    /// (the stdio writer, or the HTTP GET stream). `None` detaches it.
+    pub fn set_outbound(&self, tx: Option<UnboundedSender<String>>) {
+        *self.outbound.lock().unwrap() = tx;
+    }
+
+    pub fn send(&self, message: String) -> bool {
+        match self.outbound.lock().unwrap().as_ref() {
+            Some(tx) => tx.send(message).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn notify(&self, method: &str, params: Value) -> bool {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        self.send(message.to_string())
+    }
+
+    /// Registers an in-flight request so that `notifications/cancelled` can abort it.
+    /// The entry is removed when the returned guard is dropped.
+    pub fn track(self: &Arc<Self>, key: String) -> (AbortRegistration, InFlight) {
+        let (handle, registration) = AbortHandle::new_pair();
+        self.in_flight.lock().unwrap().insert(key.clone(), handle);
+        (registration, InFlight { session: self.clone(), key })
+    }
+
+    pub fn cancel(&self, key: &str) -> bool {
+        match self.in_flight.lock().unwrap().remove(key) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The session a request belongs to, plus the stream its related messages should use.
+#[derive(Clone)]
+pub struct Peer {
+    pub session: Arc<Session>,
+    sink: Option<UnboundedSender<String>>,
+}
+
+impl Peer {
+    pub fn new(session: Arc<Session>) -> Self {
+        Self { session, sink: None }
+    }
+
+    /// Routes messages for this request to `sink` instead of the session's channel.
+    pub fn with_sink(mut self, sink: UnboundedSender<String>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    pub fn current() -> Option<Peer> {
+        CURRENT.try_with(|p| p.clone()).ok()
+    }
+
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self, fut).await
+    }
+
+    pub fn send(&self, message: String) -> bool {
+        match &self.sink {
+            Some(tx) => tx.send(message).is_ok(),
+            None => self.session.send(message),
+        }
+    }
+
+    pub fn notify(&self, method: &str, params: Value) -> bool {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        self.send(message.to_string())
+    }
+
+    /// Sends a request to the client on this request's stream and waits for the reply.
+    /// Errors carry a readable reason: not connected, timed out, or the client's error.
+    pub async fn request(&self, method: &str, params: Value, timeout: Duration) -> Result<Value, String> {
+        let n = self.session.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let id = json!(format!("thomisidae-{n}"));
+        let key = request_key(&id);
+        let (tx, rx) = oneshot::channel();
+        self.session.pending.lock().unwrap().insert(key.clone(), tx);
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if !self.send(message.to_string()) {
+            self.session.pending.lock().unwrap().remove(&key);
+            return Err("the client is not connected".to_string());
+        }
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(error))) => Err(error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("request failed")
+                .to_string()),
+            Ok(Err(_)) => Err("the session ended".to_string()),
+            Err(_) => {
+                self.session.pending.lock().unwrap().remove(&key);
+                Err(format!("no reply to {method} within {}s", timeout.as_secs()))
+            }
+        }
+    }
+}
+
+/// A message from the client that answers one of our requests rather than making one.
+pub fn is_response(message: &Value) -> bool {
+    message.get("method").is_none()
+        && message.get("id").is_some()
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+pub struct InFlight {
+    session: Arc<Session>,
+    key: String,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.session.in_flight.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// Normalizes a JSON-RPC id (number or string) into a map key.
+pub fn request_key<T: Serialize>(id: &T) -> String {
+    serde_json::to_string(id).unwrap_or_default()
+}
+
+/// Initialized sessions that server-wide notifications are delivered to.
+#[derive(Debug, Default)]
+pub struct Peers {
+    sessions: Mutex<Vec<Weak<Session>>>,
+}
+
+impl Peers {
+    pub fn register(&self, session: &Arc<Session>) {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|w| w.strong_count() > 0);
+        if !sessions.iter().any(|w| std::ptr::eq(w.as_ptr(), Arc::as_ptr(session))) {
+            sessions.push(Arc::downgrade(session));
+        }
+    }
+
+    pub fn broadcast(&self, method: &str, params: Value) {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|w| w.strong_count() > 0);
+        for session in sessions.iter().filter_map(Weak::upgrade) {
+            session.send(message.clone());
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Sessions {
+    inner: RwLock<HashMap<String, Arc<Session>>>,
+}
+
+impl Sessions {
+    /// A session with a fresh id; it cannot be looked up until it is `insert`ed.
+    pub fn create() -> Arc<Session> {
+        Arc::new(Session::new(uuid::Uuid::new_v4().simple().to_string()))
+    }
+
+    pub async fn insert(&self, session: Arc<Session>) {
+        self.inner.write().await.insert(session.id.clone(), session);
+    }
+
+    pub async fn get(&self, id: &str) -> Option<Arc<Session>> {
+        let session = self.inner.read().await.get(id).cloned()?;
+        session.touch();
+        Some(session)
+    }
+
+    pub async fn remove(&self, id: &str) -> Option<Arc<Session>> {
+        self.inner.write().await.remove(id)
+    }
+
+    /// Removes the sessions that have been idle for at least `max` and returns them.
+    pub async fn expire(&self, max: Duration) -> Vec<Arc<Session>> {
+        let mut inner = self.inner.write().await;
+        let idle: Vec<String> = inner.iter().filter(|(_, s)| s.is_idle(max)).map(|(id, _)| id.clone()).collect();
+        idle.iter().filter_map(|id| inner.remove(id)).collect()
+    }
+
+    /// Ends every session's standalone event stream, e.g. when the server shuts down.
+    pub async fn close_all(&self) {
+        for session in self.inner.read().await.values() {
+            session.set_outbound(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::Abortable;
+
+    #[tokio::test]
+    async fn cancel_aborts_tracked_request() {
+        let session = Arc::new(Session::new("test"));
+        let key = request_key(&serde_json::json!(7));
+        let (registration, _guard) = session.track(key.clone());
+        assert!(session.cancel(&key));
+        let res = Abortable::new(std::future::pending::<()>(), registration).await;
+        assert!(res.is_err());
+        assert!(!session.cancel(&key));
+    }
+
+    #[tokio::test]
+    async fn only_idle_sessions_expire() {
+        let sessions = Sessions::default();
+        let idle = Sessions::create();
+        let busy = Sessions::create();
+        sessions.insert(idle.clone()).await;
+        sessions.insert(busy.clone()).await;
+        let (_registration, _guard) = busy.track(request_key(&serde_json::json!(1)));
+
+        assert!(sessions.expire(Duration::from_secs(3600)).await.is_empty());
+        let expired = sessions.expire(Duration::ZERO).await;
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, idle.id);
+        assert!(sessions.get(&idle.id).await.is_none());
+        assert!(sessions.get(&busy.id).await.is_some());
+    }
+}
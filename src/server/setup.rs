@@ -1,7 +1,9 @@
 use mcp_protocol_sdk::prelude::ToolHandler;
 use reqwest::Client;
+use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::{
     FetchLinksHandler,
@@ -15,13 +17,76 @@ use crate::{
     Robots,
 };
 
-use super::state::AppState;
+use super::session::Peers;
+use super::state::{AppState, TIMEOUT_ARG, ToolRegistry};
 use tokio::sync::Semaphore;
 
 use crate::config::Config;
-use crate::tools::DomainPolicy;
+use crate::environment::{get_google_api_key, get_google_cse_id};
+use crate::prompts::build_prompts;
+use crate::tools::cache::HttpCache;
+use crate::tools::net::{FilteringResolver, HttpClient, IpFilter};
+use crate::tools::{DomainPolicy, Fetcher, PageStore, SummarizeUrlHandler, summarize_url_meta};
 
-pub fn build_state(client: &Client, config: &Config) -> AppState {
+/// Every tool the server knows, whether or not it is enabled.
+pub const TOOL_NAMES: &[&str] = &["fetch_url_text", "fetch_page_links", "summarize_url", "google_search"];
+
+/// The HTTP client shared by all tools. Connections only go to addresses the
+/// `[network]` settings allow: hostnames are filtered when they are resolved, literal IPs
+/// by `tools::redirect` with the same filter. The client does not follow redirects;
+/// `tools::redirect` does, checking every hop.
+pub fn build_client(config: &Config) -> anyhow::Result<HttpClient> {
+    let ua = config
+        .http
+        .user_agent
+        .clone()
+        .or_else(|| config.robots.user_agent.clone())
+        .unwrap_or_else(|| "thomisidae/0.1.0".to_string());
+    let filter = Arc::new(IpFilter::from_config(&config.network).map_err(anyhow::Error::msg)?);
+
+    let mut builder = Client::builder()
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(FilteringResolver::new(filter.clone())))
+        .user_agent(ua);
+
+    if let Some(proxy_url) = &config.http.proxy_url
+        && !proxy_url.trim().is_empty()
+    {
+        builder = builder.proxy(reqwest::Proxy::all(proxy_url)?);
+    }
+
+    Ok(HttpClient { client: builder.build()?, ips: filter })
+}
+
+/// The HTTP cache described by `[cache]`. It is opened once and kept across config
+/// reloads; the caller flushes it on shutdown.
+pub fn open_cache(config: &Config) -> Arc<HttpCache> {
+    let dir = config.cache.enabled.then(|| config.cache_dir()).flatten();
+    let Some(dir) = dir else {
+        return Arc::new(HttpCache::disabled());
+    };
+    Arc::new(HttpCache::open(dir, config.cache.max_size_mb.saturating_mul(1024 * 1024)))
+}
+
+pub fn build_state(client: &HttpClient, cache: &Arc<HttpCache>, config: &Config) -> AppState {
+    let concurrency = Arc::new(Semaphore::new(config.max_concurrency.max(1)));
+    let pages = Arc::new(PageStore::default());
+
+    AppState::new(
+        client.clone(),
+        build_tools(client, &pages, cache, config),
+        concurrency,
+        Peers::default(),
+        pages,
+        cache.clone(),
+    )
+}
+
+/// Builds the enabled tools together with their policy and robots settings, and the
+/// prompts that go with them.
+/// Called at startup and again whenever the config file is reloaded.
+pub fn build_tools(client: &HttpClient, pages: &Arc<PageStore>, cache: &Arc<HttpCache>, config: &Config) -> ToolRegistry {
     let ua = config
         .robots
         .user_agent
@@ -37,19 +102,12 @@ pub fn build_state(client: &Client, config: &Config) -> AppState {
 
     let policy = Arc::new(DomainPolicy::from_config(config));
 
-    let fetch_text_handler = Arc::new(FetchTextHandler {
-        client: client.clone(),
-        robots: robots.clone(),
-        max_response_size: config.max_response_size,
-        policy: policy.clone(),
-    });
-    let fetch_links_handler = Arc::new(FetchLinksHandler {
-        client: client.clone(),
-        robots: robots.clone(),
-        max_response_size: config.max_response_size,
-        policy: policy.clone(),
-    });
-    let google_search_handler = Arc::new(GoogleSearchHandler::from_config(client.clone(), config));
+    let fetcher = Fetcher::new(client.clone(), policy.clone(), robots, cache.clone(), config.max_response_size);
+
+    let fetch_text_handler = Arc::new(FetchTextHandler { fetcher: fetcher.clone(), pages: pages.clone() });
+    let fetch_links_handler = Arc::new(FetchLinksHandler { fetcher: fetcher.clone() });
+    let google_search_handler = Arc::new(GoogleSearchHandler::from_config(fetcher.clone(), config));
+    let summarize_url_handler = Arc::new(SummarizeUrlHandler { fetch_text: fetch_text_handler.clone() });
 
     let mut metas = Vec::new();
     let mut handlers: HashMap<String, Arc<dyn ToolHandler + Send + Sync>> = HashMap::new();
@@ -66,9 +124,28 @@ pub fn build_state(client: &Client, config: &Config) -> AppState {
         m
     }
 
+    fn apply_tool_config(mut m: ToolMeta, config: &Config) -> ToolMeta {
+        if let Some(tool_cfg) = config.tools.get(&m.name) {
+            m.annotations
+                .get_or_insert_with(Default::default)
+                .apply(&tool_cfg.annotations);
+        }
+        let description = match config.tool_timeout(&m.name) {
+            Some(limit) => format!("Time limit for this call in milliseconds (at most {}).", limit.as_millis()),
+            None => "Time limit for this call in milliseconds.".to_string(),
+        };
+        if let Some(properties) = m.input_schema.properties.as_object_mut() {
+            properties.insert(
+                TIMEOUT_ARG.to_string(),
+                json!({ "type": "integer", "minimum": 1, "description": description }),
+            );
+        }
+        m
+    }
+
     if config.is_enabled("fetch_url_text") {
         let m = maybe_annotate_policy(fetch_text_meta(), &policy, "Domain policy:");
-        metas.push(m);
+        metas.push(apply_tool_config(m, config));
         handlers.insert(
             "fetch_url_text".into(),
             fetch_text_handler as Arc<dyn ToolHandler + Send + Sync>,
@@ -77,28 +154,97 @@ pub fn build_state(client: &Client, config: &Config) -> AppState {
 
     if config.is_enabled("fetch_page_links") {
         let m = maybe_annotate_policy(fetch_links_meta(), &policy, "Domain policy:");
-        metas.push(m);
+        metas.push(apply_tool_config(m, config));
         handlers.insert(
             "fetch_page_links".into(),
             fetch_links_handler as Arc<dyn ToolHandler + Send + Sync>,
         );
     }
 
+    if config.is_enabled("summarize_url") {
+        let m = maybe_annotate_policy(summarize_url_meta(), &policy, "Domain policy:");
+        metas.push(apply_tool_config(m, config));
+        handlers.insert(
+            "summarize_url".into(),
+            summarize_url_handler as Arc<dyn ToolHandler + Send + Sync>,
+        );
+    }
+
     if config.is_enabled("google_search") {
-        metas.push(google_search_meta());
+        metas.push(apply_tool_config(google_search_meta(), config));
         handlers.insert(
             "google_search".into(),
             google_search_handler as Arc<dyn ToolHandler + Send + Sync>,
         );
     }
 
-    let tools_meta = ToolsMeta(metas);
+    let timeouts = handlers
+        .keys()
+        .filter_map(|name| config.tool_timeout(name).map(|limit| (name.clone(), limit)))
+        .collect();
 
-    let concurrency = Arc::new(Semaphore::new(4));
-
-    AppState {
-        tools_meta,
+    ToolRegistry {
+        tools_meta: ToolsMeta(metas),
+        timeouts,
         handlers,
-        concurrency,
+        prompts: build_prompts(config),
+        fetcher,
+    }
+}
+
+/// Problems that would make the server misbehave with this config: unknown tool names,
+/// settings that cannot be parsed, and enabled tools missing what they need.
+pub fn check_config(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for name in config.features.keys().chain(config.tools.keys()) {
+        if !TOOL_NAMES.contains(&name.as_str()) {
+            problems.push(format!("Unknown tool '{name}' (known tools: {})", TOOL_NAMES.join(", ")));
+        }
+    }
+    if config.is_enabled("google_search")
+        && (get_google_api_key(config).is_none() || get_google_cse_id(config).is_none())
+    {
+        problems.push(
+            "google_search is enabled but no API key or CSE id is set ([google_search] or GOOGLE_API_KEY / GOOGLE_CSE_ID)"
+                .to_string(),
+        );
+    }
+    for domain in config.allowed_domains.iter().chain(&config.blocked_domains) {
+        if domain.contains("://") || domain.contains('/') {
+            problems.push(format!("'{domain}' in the domain policy must be a bare domain, e.g. example.com"));
+        }
+    }
+    if let Some(proxy) = config.http.proxy_url.as_deref().filter(|p| !p.trim().is_empty())
+        && let Err(err) = reqwest::Proxy::all(proxy)
+    {
+        problems.push(format!("Invalid http.proxy_url '{proxy}': {err}"));
+    }
+
+    if let Err(err) = IpFilter::from_config(&config.network) {
+        problems.push(format!("[network]: {err}"));
+    }
+
+    let port = config.server.bind.rsplit_once(':').map(|(_, port)| port.parse::<u16>());
+    if !matches!(port, Some(Ok(_))) {
+        problems.push(format!("server.bind '{}' must be host:port", config.server.bind));
+    }
+    if !config.server.path.starts_with('/') {
+        problems.push(format!("server.path '{}' must start with '/'", config.server.path));
+    }
+
+    let mut prompts: Vec<_> = config.prompts.iter().collect();
+    prompts.sort_by_key(|(name, _)| name.as_str());
+    for (name, prompt) in prompts {
+        if prompt.template.trim().is_empty() {
+            problems.push(format!("Prompt '{name}' has an empty template"));
+        }
+        for arg in prompt.arguments.iter().filter(|a| a.required && a.default.is_some()) {
+            problems.push(format!(
+                "Prompt '{name}': argument '{}' is required, so its default is never used",
+                arg.name
+            ));
+        }
     }
+    problems
 }
@@ -0,0 +1,105 @@
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use serde_json::{Value, json};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Exit status when in-flight requests were still running at the end of the grace period,
+/// or when a second signal cut the shutdown short.
+const EXIT_ABANDONED: u8 = 2;
+
+/// Tells the transports to stop taking new requests. Triggered by SIGTERM/SIGINT; the
+/// stdio transport also shuts down on its own when stdin closes.
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: Arc<watch::Sender<bool>>,
+}
+
+/// How a transport finished shutting down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drain {
+    /// Every in-flight request finished and its response was written.
+    Complete,
+    /// The grace period ran out; this many requests were abandoned without a response.
+    TimedOut(usize),
+}
+
+impl Drain {
+    pub fn exit_code(self) -> ExitCode {
+        match self {
+            Drain::Complete => ExitCode::SUCCESS,
+            Drain::TimedOut(_) => ExitCode::from(EXIT_ABANDONED),
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { requested: Arc::new(watch::channel(false).0) }
+    }
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.requested.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Resolves once a shutdown has been triggered.
+    pub async fn requested(&self) {
+        let mut rx = self.requested.subscribe();
+        let _ = rx.wait_for(|requested| *requested).await;
+    }
+
+    /// Triggers the shutdown on the first SIGTERM or SIGINT. A second signal exits
+    /// right away without waiting for in-flight requests.
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let signal = next_signal().await;
+            info!("Received {signal}, shutting down");
+            shutdown.trigger();
+            let signal = next_signal().await;
+            warn!("Received {signal} again, exiting without waiting for in-flight requests");
+            std::process::exit(EXIT_ABANDONED.into());
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn next_signal() -> &'static str {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(s) => s,
+        Err(err) => {
+            warn!("Cannot listen for SIGTERM: {err}");
+            let _ = tokio::signal::ctrl_c().await;
+            return "SIGINT";
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+        _ = terminate.recv() => "SIGTERM",
+    }
+}
+
+#[cfg(not(unix))]
+async fn next_signal() -> &'static str {
+    let _ = tokio::signal::ctrl_c().await;
+    "Ctrl-C"
+}
+
+/// Error response for a request that arrives while the server is draining.
+pub fn shutting_down_response(id: &Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": -32603, "message": "Internal error", "data": "Server is shutting down" },
+    })
+    .to_string()
+}
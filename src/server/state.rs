@@ -1,16 +1,97 @@
-use mcp_protocol_sdk::prelude::ToolHandler;
+use mcp_protocol_sdk::prelude::{McpError, McpResult, ToolHandler};
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tokio::sync::Semaphore;
 
-use crate::ToolsMeta;
+use super::logging::LogLevel;
+use super::session::Peers;
+use super::subscriptions::Subscriptions;
+use crate::prompts::Prompt;
+use crate::tools::cache::HttpCache;
+use crate::tools::net::HttpClient;
+use crate::tools::{Fetcher, PageStore, ToolsMeta};
 
-#[derive(Clone)]
 pub struct AppState {
+    pub client: HttpClient,
+    tools: RwLock<Arc<ToolRegistry>>,
+    pub concurrency: Arc<Semaphore>,
+    pub peers: Peers,
+    pub pages: Arc<PageStore>,
+    /// Shared by every registry, so cached responses survive config reloads.
+    pub cache: Arc<HttpCache>,
+    pub subscriptions: Subscriptions,
+}
+
+/// Argument every tool accepts to shorten its time limit for a single call.
+pub const TIMEOUT_ARG: &str = "timeout_ms";
+
+/// The enabled tools and prompts; swapped as a whole when the config is reloaded.
+pub struct ToolRegistry {
     pub tools_meta: ToolsMeta,
     pub handlers: HashMap<String, Arc<dyn ToolHandler + Send + Sync>>,
-    pub concurrency: Arc<Semaphore>,
+    /// Configured time limits; tools without an entry may run indefinitely.
+    pub timeouts: HashMap<String, Duration>,
+    pub prompts: Vec<Prompt>,
+    /// The fetcher the tools were built with, reused when re-checking subscribed pages.
+    pub fetcher: Fetcher,
+}
+
+impl AppState {
+    pub fn new(
+        client: HttpClient,
+        tools: ToolRegistry,
+        concurrency: Arc<Semaphore>,
+        peers: Peers,
+        pages: Arc<PageStore>,
+        cache: Arc<HttpCache>,
+    ) -> Self {
+        Self {
+            client,
+            tools: RwLock::new(Arc::new(tools)),
+            concurrency,
+            peers,
+            pages,
+            cache,
+            subscriptions: Subscriptions::default(),
+        }
+    }
+
+    pub fn tools(&self) -> Arc<ToolRegistry> {
+        self.tools.read().unwrap().clone()
+    }
+
+    /// Replaces the tool registry and returns the previous one.
+    pub fn replace_tools(&self, tools: ToolRegistry) -> Arc<ToolRegistry> {
+        std::mem::replace(&mut *self.tools.write().unwrap(), Arc::new(tools))
+    }
+}
+
+impl ToolRegistry {
+    /// The time limit for one call to `tool`. A per-call limit can only shorten the
+    /// configured one.
+    pub fn time_limit(&self, tool: &str, requested: Option<Duration>) -> Option<Duration> {
+        match (self.timeouts.get(tool).copied(), requested) {
+            (Some(configured), Some(requested)) => Some(configured.min(requested)),
+            (configured, requested) => configured.or(requested),
+        }
+    }
+
+    /// Takes the `timeout_ms` argument out of a call's arguments and returns the time
+    /// limit for the call.
+    pub fn deadline(&self, tool: &str, arguments: &mut HashMap<String, serde_json::Value>) -> McpResult<Option<Duration>> {
+        let requested = match arguments.remove(TIMEOUT_ARG) {
+            None | Some(serde_json::Value::Null) => None,
+            Some(v) => Some(
+                v.as_u64()
+                    .filter(|ms| *ms > 0)
+                    .map(Duration::from_millis)
+                    .ok_or_else(|| McpError::validation(format!("'{TIMEOUT_ARG}' must be a positive integer")))?,
+            ),
+        };
+        Ok(self.time_limit(tool, requested))
+    }
 }
 
 #[derive(Deserialize, Default)]
@@ -19,4 +100,71 @@ pub struct CallParams {
     pub name: String,
     #[serde(default)]
     pub arguments: serde_json::Value,
+    #[serde(default, rename = "_meta")]
+    pub meta: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledParams {
+    pub request_id: serde_json::Value,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeParams {
+    #[serde(default)]
+    pub protocol_version: String,
+    #[serde(default)]
+    pub client_info: serde_json::Value,
+    #[serde(default)]
+    pub capabilities: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+pub struct SetLevelParams {
+    pub level: LogLevel,
+}
+
+/// Params of `resources/read`, `resources/subscribe` and `resources/unsubscribe`.
+#[derive(Deserialize)]
+pub struct ResourceParams {
+    pub uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct GetPromptParams {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+pub struct CompleteParams {
+    #[serde(rename = "ref")]
+    pub reference: CompletionRef,
+    pub argument: CompletionArgument,
+}
+
+/// What is being completed. `ref/tool` is an extension for clients that complete
+/// tool arguments; the spec defines the prompt and resource forms.
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+pub enum CompletionRef {
+    #[serde(rename = "ref/prompt")]
+    Prompt { name: String },
+    #[serde(rename = "ref/resource")]
+    Resource {
+        #[serde(default)]
+        uri: String,
+    },
+    #[serde(rename = "ref/tool")]
+    Tool { name: String },
+}
+
+#[derive(Deserialize)]
+pub struct CompletionArgument {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
 }
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use scraper::Html;
+use serde_json::json;
+use tracing::{debug, info};
+
+use super::rpc::fetch_page;
+use super::session::Session;
+use super::state::AppState;
+use crate::tools::fetch_text::content::{is_html_content_type, is_probably_html, sanitize_html};
+use crate::tools::fetch_text::{extract_best_blocks, extract_fallback_blocks};
+use crate::tools::cache::CacheMode;
+use crate::tools::pages::resource_uri;
+use crate::tools::fetcher::{Conditional, FetchedResponse, Validators};
+
+/// Pages clients subscribed to through `resources/subscribe`, keyed by URL.
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    watches: Mutex<HashMap<String, Watch>>,
+}
+
+#[derive(Debug, Default)]
+struct Watch {
+    subscribers: Vec<Weak<Session>>,
+    validators: Validators,
+    /// Main text of the last fetched version; `None` until the first check.
+    fingerprint: Option<String>,
+}
+
+impl Subscriptions {
+    /// Returns true when the URL was not watched yet and needs a first check.
+    pub fn subscribe(&self, url: &str, session: &Arc<Session>) -> bool {
+        let mut watches = self.watches.lock().unwrap();
+        let is_new = !watches.contains_key(url);
+        let watch = watches.entry(url.to_string()).or_default();
+        if !watch.subscribers.iter().any(|w| std::ptr::eq(w.as_ptr(), Arc::as_ptr(session))) {
+            watch.subscribers.push(Arc::downgrade(session));
+        }
+        is_new
+    }
+
+    pub fn unsubscribe(&self, url: &str, session: &Arc<Session>) {
+        let mut watches = self.watches.lock().unwrap();
+        if let Some(watch) = watches.get_mut(url) {
+            watch.subscribers.retain(|w| !std::ptr::eq(w.as_ptr(), Arc::as_ptr(session)));
+            if watch.subscribers.is_empty() {
+                watches.remove(url);
+            }
+        }
+    }
+
+    /// Watched URLs, dropping watches whose sessions have all gone away.
+    fn urls(&self) -> Vec<String> {
+        let mut watches = self.watches.lock().unwrap();
+        watches.retain(|_, w| {
+            w.subscribers.retain(|s| s.strong_count() > 0);
+            !w.subscribers.is_empty()
+        });
+        watches.keys().cloned().collect()
+    }
+
+    fn validators(&self, url: &str) -> Option<Validators> {
+        self.watches.lock().unwrap().get(url).map(|w| w.validators.clone())
+    }
+
+    /// Stores the latest version and returns the sessions to notify if the text
+    /// differs from a previously seen one.
+    fn record(&self, url: &str, validators: Validators, fingerprint: String) -> Vec<Arc<Session>> {
+        let mut watches = self.watches.lock().unwrap();
+        let Some(watch) = watches.get_mut(url) else {
+            return Vec::new();
+        };
+        watch.validators = validators;
+        let changed = watch.fingerprint.as_ref().is_some_and(|f| *f != fingerprint);
+        watch.fingerprint = Some(fingerprint);
+        if !changed {
+            return Vec::new();
+        }
+        watch.subscribers.iter().filter_map(Weak::upgrade).collect()
+    }
+}
+
+/// Re-checks every subscribed page each `interval`.
+pub fn spawn_resource_poller(state: Arc<AppState>, interval: Duration) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            for url in state.subscriptions.urls() {
+                check(&state, &url).await;
+            }
+        }
+    });
+}
+
+/// Fetches `url` with the validators from the last check and notifies subscribers
+/// when the extracted main text changed. The domain policy and robots.txt apply as
+/// they do for `fetch_url_text`.
+pub async fn check(state: &AppState, url: &str) {
+    let Some(validators) = state.subscriptions.validators(url) else {
+        return;
+    };
+    let tools = state.tools();
+    match tools.fetcher.get_if_modified(url, &validators).await {
+        Ok(Conditional::NotModified) => debug!(url, "Subscribed page not modified"),
+        Ok(Conditional::Modified(resp, next)) => {
+            let subscribers = state.subscriptions.record(url, next, main_text(&resp));
+            if subscribers.is_empty() {
+                return;
+            }
+            info!(url, subscribers = subscribers.len(), "Subscribed page changed");
+            // The cached copy may still be fresh; the page just changed, so bypass it.
+            if state.pages.get(url).is_some() && fetch_page(state, url, CacheMode::NoCache).await.is_err() {
+                debug!(url, "Failed to refresh stored page");
+            }
+            let params = json!({ "uri": resource_uri(url) });
+            for session in subscribers {
+                session.notify("notifications/resources/updated", params.clone());
+            }
+        }
+        Err(e) => debug!(url, error = %e, "Failed to re-check subscribed page"),
+    }
+}
+
+/// The text used to decide whether a page changed: the main content blocks for
+/// HTML, so that rotating ads or timestamps outside them do not count.
+fn main_text(resp: &FetchedResponse) -> String {
+    if !(is_html_content_type(resp.content_type.as_deref()) || is_probably_html(&resp.body)) {
+        return resp.body.clone();
+    }
+    let doc = Html::parse_document(&sanitize_html(&resp.body));
+    extract_best_blocks(&doc)
+        .unwrap_or_else(|| extract_fallback_blocks(&doc))
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_notifies_only_on_change() {
+        let subs = Subscriptions::default();
+        let session = Arc::new(Session::new("test"));
+        let url = "https://example.com/status";
+        assert!(subs.subscribe(url, &session));
+        assert!(!subs.subscribe(url, &session));
+
+        assert!(subs.record(url, Validators::default(), "v1".into()).is_empty());
+        assert!(subs.record(url, Validators::default(), "v1".into()).is_empty());
+        assert_eq!(subs.record(url, Validators::default(), "v2".into()).len(), 1);
+
+        subs.unsubscribe(url, &session);
+        assert!(subs.urls().is_empty());
+    }
+}
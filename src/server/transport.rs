@@ -1,27 +1,78 @@
 use anyhow::Result;
 use jsonrpc_v2::{Data, MapRouter, Server};
-use reqwest::Client;
-use std::io;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::BufReader;
+use tracing::{info, warn};
 
+use super::reload::spawn_config_watcher;
 use super::rpc;
 use super::setup::build_state;
-use crate::config::Config;
+use super::shutdown::{Drain, Shutdown};
+use super::subscriptions::spawn_resource_poller;
+use crate::config::{Config, ServerConfig, TransportKind};
+use crate::tools::cache::HttpCache;
+use crate::tools::net::HttpClient;
 
-pub fn build_server(client: &Client, config: &Config) -> Arc<Server<MapRouter>> {
-    let state = build_state(client, config);
+/// How often the HTTP cache index is written while serving, so a crash loses little.
+const CACHE_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn build_server(client: &HttpClient, cache: &Arc<HttpCache>, config: &Config) -> Arc<Server<MapRouter>> {
+    let state = Arc::new(build_state(client, cache, config));
+
+    if config.server.watch_config
+        && let Some(path) = &config.source
+    {
+        spawn_config_watcher(state.clone(), path.clone());
+    }
+    spawn_resource_poller(state.clone(), Duration::from_secs(config.resources.poll_interval_secs.max(10)));
+    let cache = cache.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(CACHE_FLUSH_INTERVAL).await;
+            cache.flush().await;
+        }
+    });
 
     Server::new()
-        .with_data(Data::new(state))
+        .with_data(Data(state))
         .with_method("initialize", rpc::initialize)
+        .with_method("notifications/initialized", rpc::initialized)
         .with_method("tools/list", rpc::tools_list)
         .with_method("tools/call", rpc::tools_call)
+        .with_method("prompts/list", rpc::prompts_list)
+        .with_method("prompts/get", rpc::prompts_get)
+        .with_method("completion/complete", rpc::completion_complete)
+        .with_method("resources/list", rpc::resources_list)
+        .with_method("resources/templates/list", rpc::resources_templates_list)
+        .with_method("resources/read", rpc::resources_read)
+        .with_method("resources/subscribe", rpc::resources_subscribe)
+        .with_method("resources/unsubscribe", rpc::resources_unsubscribe)
+        .with_method("notifications/cancelled", rpc::cancelled)
+        .with_method("logging/setLevel", rpc::set_log_level)
         .finish()
 }
 
-pub async fn run_with_server(server: Arc<Server<jsonrpc_v2::MapRouter>>) -> Result<()> {
+/// Serves until the client disconnects (stdio) or SIGTERM/SIGINT arrives, then lets
+/// in-flight requests finish within `shutdown_grace_secs`.
+pub async fn run_with_server(server: Arc<Server<MapRouter>>, cfg: &ServerConfig) -> Result<Drain> {
+    let shutdown = Shutdown::default();
+    shutdown.listen_for_signals();
+    let grace = Duration::from_secs(cfg.shutdown_grace_secs);
+    let drain = match cfg.transport {
+        TransportKind::Stdio => run_stdio(server, &shutdown, grace).await?,
+        TransportKind::Http => crate::server::http::run_http(server, cfg, &shutdown, grace).await?,
+    };
+    match drain {
+        Drain::Complete => info!("Shut down cleanly"),
+        Drain::TimedOut(0) => warn!("Shut down before all requests finished"),
+        Drain::TimedOut(n) => warn!("Shut down with {n} request(s) still running"),
+    }
+    Ok(drain)
+}
+
+async fn run_stdio(server: Arc<Server<MapRouter>>, shutdown: &Shutdown, grace: Duration) -> Result<Drain> {
     let stdin = BufReader::new(tokio::io::stdin());
-    let stdout = io::stdout();
-    crate::server::io::run_with_io(server, stdin, stdout).await
-}
\ No newline at end of file
+    let stdout = tokio::io::stdout();
+    crate::server::io::run_with_io(server, stdin, stdout, shutdown, grace).await
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::Config;
+
+tokio::task_local! {
+    static CURRENT: Approver;
+}
+
+type Ask = Arc<dyn Fn(String) -> BoxFuture<'static, bool> + Send + Sync>;
+
+/// Asks the user whether a domain outside the allow list may be fetched. Set by the
+/// server for requests from clients that support elicitation; without it, such
+/// domains are simply refused.
+#[derive(Clone)]
+pub struct Approver {
+    ask: Ask,
+}
+
+impl Approver {
+    pub fn new<F, Fut>(ask: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        Self { ask: Arc::new(move |host| Box::pin(ask(host))) }
+    }
+
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self, fut).await
+    }
+}
+
+pub async fn approve(host: &str) -> bool {
+    let Ok(approver) = CURRENT.try_with(|a| a.clone()) else {
+        return false;
+    };
+    (approver.ask)(host.to_string()).await
+}
+
+const APPROVED_FILE: &str = "approved_domains.toml";
+
+/// Domains the user chose to "always allow", kept in `approved_domains.toml` next to
+/// the config file. Without a config file (or with persistence off) nothing is stored.
+#[derive(Debug, Default)]
+pub struct ApprovedDomains {
+    path: Option<PathBuf>,
+    domains: Mutex<BTreeSet<String>>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ApprovedFile {
+    #[serde(default)]
+    domains: BTreeSet<String>,
+}
+
+impl ApprovedDomains {
+    pub fn from_config(config: &Config) -> Self {
+        let path = config
+            .source
+            .as_ref()
+            .filter(|_| config.persist_domain_approvals)
+            .and_then(|source| source.parent().map(|dir| dir.join(APPROVED_FILE)));
+        let domains = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|contents| match toml::from_str::<ApprovedFile>(&contents) {
+                Ok(file) => Some(file.domains),
+                Err(err) => {
+                    warn!("Ignoring unreadable {APPROVED_FILE}: {err}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self { path, domains: Mutex::new(domains) }
+    }
+
+    pub fn persists(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn contains(&self, host: &str) -> bool {
+        self.domains.lock().unwrap().contains(host)
+    }
+
+    pub fn add(&self, host: &str) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut domains = self.domains.lock().unwrap();
+        domains.insert(host.to_string());
+        let file = ApprovedFile { domains: domains.clone() };
+        let written = toml::to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| std::fs::write(path, contents).map_err(|e| e.to_string()));
+        if let Err(err) = written {
+            warn!("Failed to write {}: {err}", path.display());
+        }
+    }
+}
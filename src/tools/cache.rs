@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use mcp_protocol_sdk::prelude::*;
+use reqwest::header::{
+    AGE, CACHE_CONTROL, DATE, ETAG, EXPIRES, HeaderMap, HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, VARY,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+const INDEX_FILE: &str = "index.json";
+/// Marks a directory as the cache's own (see <https://bford.info/cachedir/>). Nothing is
+/// ever deleted from a directory without it.
+const MARKER_FILE: &str = "CACHEDIR.TAG";
+const MARKER: &str = "Signature: 8a477f597d28d172789f06886806bc55\n# HTTP response cache created by thomisidae.\n";
+/// Subdirectory holding the response bodies, one `<pid>-<n>.body` file each.
+const BODIES_DIR: &str = "entries";
+const BODY_SUFFIX: &str = ".body";
+/// Bodies no index entry refers to are only removed once they are this old, so files
+/// another process (a running server) wrote since its last index flush are left alone.
+const ORPHAN_GRACE: Duration = Duration::from_secs(60 * 60);
+/// Upper bound for the freshness guessed from `Last-Modified` when a response has no
+/// explicit lifetime (a tenth of its age, as RFC 9111 suggests).
+const MAX_HEURISTIC_SECS: u64 = 24 * 60 * 60;
+
+/// How a call uses the HTTP cache, from its `cache` argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheMode {
+    /// Use a fresh cached copy; revalidate a stale one.
+    #[default]
+    Default,
+    /// Always ask the server, revalidating the cached copy when there is one.
+    NoCache,
+    /// Never request the page; fail when it is not cached.
+    OnlyIfCached,
+}
+
+impl CacheMode {
+    pub fn from_arguments(arguments: &HashMap<String, Value>) -> McpResult<Self> {
+        match arguments.get("cache").and_then(Value::as_str) {
+            None | Some("default") => Ok(Self::Default),
+            Some("no-cache") => Ok(Self::NoCache),
+            Some("only-if-cached") => Ok(Self::OnlyIfCached),
+            Some(other) => Err(McpError::validation(format!(
+                "Invalid cache mode '{other}' (expected default, no-cache or only-if-cached)"
+            ))),
+        }
+    }
+
+    /// The `cache` argument value selecting this mode.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::NoCache => "no-cache",
+            Self::OnlyIfCached => "only-if-cached",
+        }
+    }
+}
+
+/// Schema of the `cache` argument accepted by every tool that fetches.
+pub fn cache_arg_schema() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["default", "no-cache", "only-if-cached"],
+        "default": "default",
+        "description": "default: reuse a fresh cached response and revalidate a stale one; no-cache: always revalidate with the server; only-if-cached: never request the page, fail if it is not cached"
+    })
+}
+
+/// On-disk cache of successful GET responses, bounded by the total size of the bodies
+/// and evicted least recently used first. Each body is its own file; the index that
+/// maps keys to them is written by `flush`, so entries stored after the last flush are
+/// lost (and their files eventually removed) if the process dies.
+#[derive(Debug)]
+pub struct HttpCache {
+    /// `None` when the cache is disabled.
+    dir: Option<PathBuf>,
+    max_size: u64,
+    index: Mutex<Index>,
+    /// Held while the index is written, so the periodic and the final flush do not race.
+    flushing: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    /// Counter for body file names and for recency.
+    clock: u64,
+    entries: HashMap<String, Entry>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    file: String,
+    size: u64,
+    final_url: String,
+    redirects: Vec<String>,
+    headers: Vec<(String, String)>,
+    /// Unix time (seconds) until which the response may be used without revalidation.
+    fresh_until: u64,
+    no_cache: bool,
+    must_revalidate: bool,
+    last_used: u64,
+}
+
+/// A response read back from the cache.
+#[derive(Debug)]
+pub struct Cached {
+    pub final_url: String,
+    pub redirects: Vec<String>,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// Whether it may be used without asking the server.
+    pub fresh: bool,
+    /// A stale copy must not be used without the server's consent.
+    pub must_revalidate: bool,
+}
+
+impl Cached {
+    /// `If-None-Match` / `If-Modified-Since` headers to revalidate this response.
+    pub fn validators(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = self.headers.get(ETAG) {
+            headers.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = self.headers.get(LAST_MODIFIED) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+        headers
+    }
+}
+
+impl HttpCache {
+    pub fn disabled() -> Self {
+        Self { dir: None, max_size: 0, index: Mutex::new(Index::default()), flushing: Default::default() }
+    }
+
+    /// Opens the cache in `dir`, dropping index entries whose body is gone and old body
+    /// files no entry refers to. Falls back to a disabled cache if `dir` is unusable, or
+    /// if it already holds other files and was not created by the cache.
+    pub fn open(dir: PathBuf, max_size: u64) -> Self {
+        let marker = dir.join(MARKER_FILE);
+        if !marker.is_file() && std::fs::read_dir(&dir).is_ok_and(|mut files| files.next().is_some()) {
+            warn!(
+                "HTTP cache disabled: {} is not empty and has no {MARKER_FILE}; set [cache] dir to a dedicated directory",
+                dir.display()
+            );
+            return Self::disabled();
+        }
+        if let Err(err) = create_private_dir(&dir) {
+            warn!("HTTP cache disabled, cannot create {}: {err}", dir.display());
+            return Self::disabled();
+        }
+        if let Err(reason) = ensure_private(&dir) {
+            warn!("HTTP cache disabled: {} {reason}", dir.display());
+            return Self::disabled();
+        }
+        let created = std::fs::create_dir_all(dir.join(BODIES_DIR))
+            .and_then(|_| if marker.is_file() { Ok(()) } else { std::fs::write(&marker, MARKER) });
+        if let Err(err) = created {
+            warn!("HTTP cache disabled, cannot create {}: {err}", dir.display());
+            return Self::disabled();
+        }
+        let mut index = match std::fs::read_to_string(dir.join(INDEX_FILE)) {
+            Ok(contents) => serde_json::from_str::<Index>(&contents).unwrap_or_else(|err| {
+                warn!("Ignoring unreadable HTTP cache index: {err}");
+                Index::default()
+            }),
+            Err(_) => Index::default(),
+        };
+        index.entries.retain(|_, e| body_path(&dir, &e.file).is_file());
+        if let Ok(files) = std::fs::read_dir(dir.join(BODIES_DIR)) {
+            let cutoff = SystemTime::now() - ORPHAN_GRACE;
+            for file in files.flatten() {
+                let name = file.file_name().to_string_lossy().into_owned();
+                let old = file.metadata().and_then(|m| m.modified()).is_ok_and(|t| t < cutoff);
+                if name.ends_with(BODY_SUFFIX) && old && !index.entries.values().any(|e| e.file == name) {
+                    let _ = std::fs::remove_file(file.path());
+                }
+            }
+        }
+        debug!(dir = %dir.display(), entries = index.entries.len(), "Opened HTTP cache");
+        Self { dir: Some(dir), max_size, index: Mutex::new(index), flushing: Default::default() }
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Cached> {
+        let dir = self.dir.as_ref()?;
+        let entry = {
+            let mut index = self.index.lock().unwrap();
+            index.clock += 1;
+            let clock = index.clock;
+            let entry = index.entries.get_mut(key)?;
+            entry.last_used = clock;
+            let entry = entry.clone();
+            index.dirty = true;
+            entry
+        };
+        let Ok(body) = tokio::fs::read(body_path(dir, &entry.file)).await else {
+            self.remove(key).await;
+            return None;
+        };
+        let headers = header_map(&entry.headers);
+        Some(Cached {
+            final_url: entry.final_url,
+            redirects: entry.redirects,
+            headers,
+            body,
+            fresh: !entry.no_cache && unix_now() < entry.fresh_until,
+            must_revalidate: entry.must_revalidate,
+        })
+    }
+
+    /// Stores a successful response, or drops the cached copy when the response may not
+    /// be stored (`no-store`, `Vary: *`, or nothing to reuse or revalidate it with).
+    /// The body is written before the index is locked, so other fetches do not wait on it.
+    pub async fn put(&self, key: &str, final_url: &str, redirects: &[String], headers: &HeaderMap, body: &[u8]) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let freshness = Freshness::of(headers, SystemTime::now());
+        if !freshness.storable || body.len() as u64 > self.max_size {
+            self.remove(key).await;
+            return;
+        }
+
+        let clock = {
+            let mut index = self.index.lock().unwrap();
+            index.clock += 1;
+            index.clock
+        };
+        // The pid keeps names unique when several processes share the directory.
+        let file = format!("{}-{clock}{BODY_SUFFIX}", std::process::id());
+        if let Err(err) = tokio::fs::write(body_path(dir, &file), body).await {
+            warn!("Failed to write HTTP cache entry: {err}");
+            return;
+        }
+        let entry = Entry {
+            file,
+            size: body.len() as u64,
+            final_url: final_url.to_string(),
+            redirects: redirects.to_vec(),
+            headers: header_pairs(headers),
+            fresh_until: unix_now() + freshness.lifetime,
+            no_cache: freshness.no_cache,
+            must_revalidate: freshness.must_revalidate,
+            last_used: clock,
+        };
+        let unused = {
+            let mut index = self.index.lock().unwrap();
+            let mut unused: Vec<Entry> = index.entries.insert(key.to_string(), entry).into_iter().collect();
+            unused.extend(self.evict(&mut index));
+            index.dirty = true;
+            unused
+        };
+        remove_bodies(dir, unused).await;
+        debug!(key, "Stored response in HTTP cache");
+    }
+
+    /// Updates a cached response after the server answered `304 Not Modified`: headers
+    /// sent with the 304 replace the stored ones and the freshness starts over.
+    pub async fn refresh(&self, key: &str, not_modified: &HeaderMap) -> Option<Cached> {
+        {
+            let mut index = self.index.lock().unwrap();
+            let entry = index.entries.get_mut(key)?;
+            let mut headers = header_map(&entry.headers);
+            for name in not_modified.keys() {
+                headers.remove(name);
+            }
+            for (name, value) in not_modified {
+                headers.append(name, value.clone());
+            }
+            let freshness = Freshness::of(&headers, SystemTime::now());
+            entry.headers = header_pairs(&headers);
+            entry.fresh_until = unix_now() + freshness.lifetime;
+            entry.no_cache = freshness.no_cache;
+            entry.must_revalidate = freshness.must_revalidate;
+            index.dirty = true;
+        }
+        self.get(key).await
+    }
+
+    async fn remove(&self, key: &str) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let old = {
+            let mut index = self.index.lock().unwrap();
+            let old = index.entries.remove(key);
+            index.dirty |= old.is_some();
+            old
+        };
+        remove_bodies(dir, old).await;
+    }
+
+    /// Drops least recently used entries until the bodies fit; returns them so their
+    /// files can be deleted once the lock is released.
+    fn evict(&self, index: &mut Index) -> Vec<Entry> {
+        let mut evicted = Vec::new();
+        let mut size: u64 = index.entries.values().map(|e| e.size).sum();
+        while size > self.max_size {
+            let Some(key) = index.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else {
+                break;
+            };
+            if let Some(old) = index.entries.remove(&key) {
+                size -= old.size;
+                debug!(key, "Evicted from HTTP cache");
+                evicted.push(old);
+            }
+        }
+        evicted
+    }
+
+    /// Writes the index if anything changed since the last flush.
+    pub async fn flush(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let _flushing = self.flushing.lock().await;
+        let json = {
+            let mut index = self.index.lock().unwrap();
+            if !index.dirty {
+                return;
+            }
+            index.dirty = false;
+            serde_json::to_vec(&*index)
+        };
+        let tmp = dir.join(format!("{INDEX_FILE}.tmp"));
+        let written = match json {
+            Ok(json) => match tokio::fs::write(&tmp, json).await {
+                Ok(()) => tokio::fs::rename(&tmp, dir.join(INDEX_FILE)).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            },
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(err) = written {
+            warn!("Failed to write HTTP cache index: {err}");
+            self.index.lock().unwrap().dirty = true;
+        }
+    }
+}
+
+/// What a response's caching headers allow.
+#[derive(Debug, PartialEq, Eq)]
+struct Freshness {
+    storable: bool,
+    /// Seconds the response stays fresh from now.
+    lifetime: u64,
+    no_cache: bool,
+    must_revalidate: bool,
+}
+
+impl Freshness {
+    fn of(headers: &HeaderMap, now: SystemTime) -> Self {
+        let directives: Vec<(String, Option<String>)> = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|d| match d.split_once('=') {
+                Some((name, value)) => (name.trim().to_ascii_lowercase(), Some(value.trim().trim_matches('"').to_string())),
+                None => (d.trim().to_ascii_lowercase(), None),
+            })
+            .collect();
+        let has = |name: &str| directives.iter().any(|(n, _)| n == name);
+        let date = |name| headers.get(name).and_then(|v| v.to_str().ok()).and_then(|v| httpdate::parse_http_date(v).ok());
+        let secs_between = |from: SystemTime, to: SystemTime| to.duration_since(from).map_or(0, |d| d.as_secs());
+
+        let sent_at = date(DATE).unwrap_or(now);
+        let max_age = directives
+            .iter()
+            .find(|(n, _)| n == "max-age")
+            .and_then(|(_, v)| v.as_deref()?.parse::<u64>().ok());
+        let lifetime = match (max_age, headers.get(EXPIRES), date(LAST_MODIFIED)) {
+            (Some(max_age), _, _) => max_age,
+            // An unparsable Expires (often "0") means already expired.
+            (None, Some(_), _) => date(EXPIRES).map_or(0, |expires| secs_between(sent_at, expires)),
+            (None, None, Some(modified)) => (secs_between(modified, sent_at) / 10).min(MAX_HEURISTIC_SECS),
+            (None, None, None) => 0,
+        };
+        let age = headers
+            .get(AGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        let lifetime = lifetime.saturating_sub(age);
+
+        let vary_all = headers
+            .get_all(VARY)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .any(|v| v.split(',').any(|h| h.trim() == "*"));
+        let revalidatable = headers.contains_key(ETAG) || headers.contains_key(LAST_MODIFIED);
+        Self {
+            storable: !has("no-store") && !vary_all && (lifetime > 0 || revalidatable),
+            lifetime,
+            no_cache: has("no-cache"),
+            must_revalidate: has("must-revalidate") || has("proxy-revalidate"),
+        }
+    }
+}
+
+/// Creates `dir` (and missing parents) readable only by the current user.
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Cached pages are served as if just fetched, so the directory must belong to the
+/// current user and be closed to everyone else. Looser permissions are tightened.
+#[cfg(unix)]
+fn ensure_private(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let meta = std::fs::metadata(dir).map_err(|e| format!("cannot be read: {e}"))?;
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    if meta.uid() != unsafe { libc::geteuid() } {
+        return Err("is owned by another user".to_string());
+    }
+    if meta.mode() & 0o077 != 0 {
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .map_err(|e| format!("cannot be made private: {e}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn ensure_private(_dir: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+async fn remove_bodies(dir: &Path, entries: impl IntoIterator<Item = Entry>) {
+    for entry in entries {
+        let _ = tokio::fs::remove_file(body_path(dir, &entry.file)).await;
+    }
+}
+
+fn body_path(dir: &Path, file: &str) -> PathBuf {
+    dir.join(BODIES_DIR).join(file)
+}
+
+fn header_map(pairs: &[(String, String)]) -> HeaderMap {
+    pairs
+        .iter()
+        .filter_map(|(name, value)| Some((HeaderName::from_bytes(name.as_bytes()).ok()?, HeaderValue::from_str(value).ok()?)))
+        .collect()
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (HeaderName::from_static(name), HeaderValue::from_str(value).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn freshness_follows_cache_headers() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let date = httpdate::fmt_http_date(now);
+        let lifetime = |pairs: &[(&'static str, &str)]| Freshness::of(&headers(pairs), now);
+
+        let f = lifetime(&[("cache-control", "public, max-age=600"), ("age", "100")]);
+        assert_eq!((f.storable, f.lifetime), (true, 500));
+        assert!(!lifetime(&[("cache-control", "no-store, max-age=600")]).storable);
+        assert!(!lifetime(&[("cache-control", "max-age=600"), ("vary", "Accept, *")]).storable);
+
+        let expires = httpdate::fmt_http_date(now + Duration::from_secs(60));
+        assert_eq!(lifetime(&[("date", &date), ("expires", &expires)]).lifetime, 60);
+        assert_eq!(lifetime(&[("expires", "0"), ("etag", "\"a\"")]).lifetime, 0);
+
+        let modified = httpdate::fmt_http_date(now - Duration::from_secs(1000));
+        let f = lifetime(&[("date", &date), ("last-modified", &modified)]);
+        assert_eq!((f.storable, f.lifetime), (true, 100));
+
+        let f = lifetime(&[("cache-control", "no-cache"), ("etag", "\"a\"")]);
+        assert!(f.storable && f.no_cache);
+        assert!(!lifetime(&[("content-type", "text/html")]).storable);
+    }
+
+    #[tokio::test]
+    async fn cache_evicts_least_recently_used_and_survives_reopen() {
+        let dir = std::env::temp_dir().join(format!("thomisidae-cache-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let fresh = headers(&[("cache-control", "max-age=600")]);
+
+        let cache = HttpCache::open(dir.clone(), 10);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        }
+        cache.put("a", "https://a/", &[], &fresh, b"aaaa").await;
+        cache.put("b", "https://b/", &[], &fresh, b"bbbb").await;
+        assert!(cache.get("a").await.is_some_and(|c| c.fresh && c.body == b"aaaa"));
+        cache.put("c", "https://c/", &[], &fresh, b"cccc").await;
+        assert!(cache.get("b").await.is_none(), "least recently used entry is evicted");
+        cache.put("big", "https://big/", &[], &fresh, &[0; 11]).await;
+        assert!(cache.get("big").await.is_none());
+        cache.flush().await;
+
+        let reopened = HttpCache::open(dir.clone(), 10);
+        assert_eq!(reopened.get("c").await.map(|c| c.final_url), Some("https://c/".to_string()));
+        assert!(reopened.get("a").await.is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cache_only_prunes_its_own_files() {
+        let dir = std::env::temp_dir().join(format!("thomisidae-cache-prune-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("config.toml"), "").unwrap();
+        assert!(HttpCache::open(dir.clone(), 10).dir.is_none(), "a foreign directory is not used");
+        assert!(dir.join("config.toml").is_file());
+        std::fs::remove_file(dir.join("config.toml")).unwrap();
+
+        HttpCache::open(dir.clone(), 10);
+        let bodies = dir.join(BODIES_DIR);
+        let stale = SystemTime::now() - ORPHAN_GRACE * 2;
+        for name in ["1-1.body", "1-2.body", "notes.txt"] {
+            std::fs::write(bodies.join(name), "x").unwrap();
+        }
+        for name in ["1-1.body", "notes.txt"] {
+            std::fs::File::options().write(true).open(bodies.join(name)).unwrap().set_modified(stale).unwrap();
+        }
+        let cache = HttpCache::open(dir.clone(), 10);
+        assert!(cache.dir.is_some());
+        assert!(!bodies.join("1-1.body").exists(), "old orphan is removed");
+        assert!(bodies.join("1-2.body").exists(), "recent orphan may belong to another process");
+        assert!(bodies.join("notes.txt").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
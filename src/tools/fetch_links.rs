@@ -1,34 +1,54 @@
 use async_trait::async_trait;
 use mcp_protocol_sdk::prelude::*;
-use reqwest::Client;
 use scraper::{Html, Selector};
 use serde_json::Value;
-use std::{collections::{HashMap, HashSet}, sync::Arc};
-use super::utils::{fetch_html, required_str_arg, text_tool_result};
+use std::collections::{HashMap, HashSet};
+use super::utils::{execution_result, required_str_arg, structured_tool_result};
 use url::Url;
-use super::meta::ToolMeta;
+use super::meta::{ToolAnnotations, ToolInputSchema, ToolMeta};
 use std::sync::OnceLock;
-use super::robots::Robots;
-use super::policy::{DomainPolicy, ensure_allowed};
+use super::cache::{CacheMode, cache_arg_schema};
+use super::fetcher::{Fetcher, status_line};
+use super::progress;
 
 static META: OnceLock<ToolMeta> = OnceLock::new();
 
 pub fn meta() -> ToolMeta {
     META.get_or_init(|| {
-        ToolMeta::new_with_default_schema(
+        let properties = serde_json::json!({
+            "url": { "type": "string" },
+            "same_domain": { "type": "boolean", "default": false },
+            "format": { "type": "string", "enum": ["text", "json"], "default": "text" },
+            "allow_error_status": { "type": "boolean", "default": false, "description": "Return the links even when the server answers with a non-2xx status" },
+            "cache": cache_arg_schema()
+        });
+        ToolMeta::new(
             "fetch_page_links",
             "Fetch Page Links",
             "Fetches links from a page",
+            ToolInputSchema::new("object", properties, vec!["url".to_string()]),
         )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "final_url": { "type": "string", "description": "The URL that answered, after redirects" },
+                "redirects": { "type": "array", "items": { "type": "string" }, "description": "URLs that redirected, in order, starting with url" },
+                "status": { "type": "integer", "description": "HTTP status of the final response" },
+                "elapsed_ms": { "type": "integer", "description": "Time taken by the fetch, redirects included" },
+                "from_cache": { "type": "boolean", "description": "Whether the page was served from the HTTP cache" },
+                "links": { "type": "array", "items": { "type": "string" } },
+                "count": { "type": "integer" }
+            },
+            "required": ["url", "final_url", "redirects", "status", "elapsed_ms", "from_cache", "links", "count"]
+        }))
+        .with_annotations(ToolAnnotations::read_only_web())
     })
     .clone()
 }
 
 pub struct FetchLinksHandler {
-    pub client: Client,
-    pub robots: Arc<Robots>,
-    pub max_response_size: usize,
-    pub policy: Arc<DomainPolicy>,
+    pub fetcher: Fetcher,
 }
 
 #[async_trait]
@@ -36,27 +56,43 @@ impl ToolHandler for FetchLinksHandler {
     async fn call(&self, arguments: HashMap<String, Value>) -> McpResult<ToolResult> {
 
         let url = required_str_arg(&arguments, "url")?;
-        let base_url = Url::parse(&url).map_err(|e| McpError::validation(format!("Invalid url: {e}")))?;
-        ensure_allowed(&self.policy, &base_url)?;
-
         let same_domain = arguments.get("same_domain").and_then(|v| v.as_bool()).unwrap_or(false);
         let format = arguments
             .get("format")
             .and_then(|v| v.as_str())
             .unwrap_or("text");
+        let allow_error_status = arguments.get("allow_error_status").and_then(|v| v.as_bool()).unwrap_or(false);
+        let cache = CacheMode::from_arguments(&arguments)?;
+
+        execution_result(self.run(&url, same_domain, format, allow_error_status, cache).await)
+    }
+}
 
-        if !self.robots.allow(&base_url).await? {
-            return Err(McpError::validation("Blocked by robots.txt".to_string()));
+impl FetchLinksHandler {
+    async fn run(
+        &self,
+        url: &str,
+        same_domain: bool,
+        format: &str,
+        allow_error_status: bool,
+        cache: CacheMode,
+    ) -> McpResult<ToolResult> {
+        let base_url = Url::parse(url).map_err(|e| McpError::validation(format!("Invalid url: {e}")))?;
+        let mut fetched = self.fetcher.get(url, true, cache).await?;
+        if !allow_error_status {
+            fetched = fetched.error_for_status()?;
         }
-    let html = fetch_html(&self.client, &url, self.max_response_size).await?;
-        let doc = Html::parse_document(&html);
+        progress::step("Extracting links");
+        // Relative links resolve against the page that answered, not the requested URL.
+        let page_url = Url::parse(&fetched.final_url).map_err(|e| McpError::internal(e.to_string()))?;
+        let doc = Html::parse_document(&fetched.body);
         let a = Selector::parse("a[href]").map_err(|e| McpError::internal(e.to_string()))?;
-        let base_domain = base_url.domain();
+        let base_domain = page_url.domain();
         let mut seen: HashSet<String> = HashSet::new();
         let links: Vec<String> = doc
             .select(&a)
             .filter_map(|el| el.value().attr("href"))
-            .filter_map(|href| base_url.join(href).or_else(|_| Url::parse(href)).ok())
+            .filter_map(|href| page_url.join(href).or_else(|_| Url::parse(href)).ok())
             .filter(|u| matches!(u.scheme(), "http" | "https"))
             .filter(|u| !same_domain || u.domain() == base_domain)
             .map(|mut u| {
@@ -64,19 +100,36 @@ impl ToolHandler for FetchLinksHandler {
                 u.to_string()
             })
             .filter_map(|s| if seen.insert(s.clone()) { Some(s) } else { None })
-            .filter(|s| Url::parse(s).map_or(true, |u| self.policy.allows_url(&u)))
+            .filter(|s| Url::parse(s).map_or(true, |u| self.fetcher.policy().allows_url(&u)))
             .collect();
 
-        match format {
-            "json" => {
-                let json_text = serde_json::to_string(&links)
-                    .map_err(|e| McpError::internal(e.to_string()))?;
-                Ok(text_tool_result(json_text))
-            }
+        let text = match format {
+            "json" => serde_json::to_string(&links).map_err(|e| McpError::internal(e.to_string()))?,
             _ => {
-                let text = links.join("\n");
-                Ok(text_tool_result(text))
+                let mut notes = Vec::new();
+                if !fetched.redirects.is_empty() {
+                    notes.push(format!("Redirected: {} -> {}", fetched.redirects.join(" -> "), fetched.final_url));
+                }
+                if !fetched.status.is_success() {
+                    notes.push(format!("Status: {}", status_line(fetched.status)));
+                }
+                if notes.is_empty() {
+                    links.join("\n")
+                } else {
+                    format!("{}\n\n{}", notes.join("\n"), links.join("\n"))
+                }
             }
-        }
+        };
+        let structured = serde_json::json!({
+            "url": base_url.as_str(),
+            "final_url": fetched.final_url,
+            "redirects": fetched.redirects,
+            "status": fetched.status.as_u16(),
+            "elapsed_ms": fetched.elapsed.as_millis() as u64,
+            "from_cache": fetched.from_cache,
+            "count": links.len(),
+            "links": links,
+        });
+        Ok(structured_tool_result(text, structured))
     }
 }
@@ -1,14 +1,47 @@
+/// One page of a longer text, addressed by byte offsets into the full content.
+pub struct Chunk<'a> {
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+    pub total: usize,
+}
+
+impl Chunk<'_> {
+    pub fn next_start(&self) -> Option<usize> {
+        (self.end < self.total).then_some(self.end)
+    }
+}
+
+/// Slices `content` at `start_index`, snapping both ends to char boundaries.
+pub fn chunk(content: &str, start_index: usize, max_length: usize) -> Option<Chunk<'_>> {
+    let total = content.len();
+    if start_index >= total {
+        return None;
+    }
+    let start = floor_char_boundary(content, start_index);
+    let mut end = floor_char_boundary(content, start.saturating_add(max_length).min(total));
+    if end <= start {
+        end = content[start..].chars().next().map_or(total, |c| start + c.len_utf8());
+    }
+    Some(Chunk { text: &content[start..end], start, end, total })
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
 pub fn truncate_with_hint(content: &str, start_index: usize, max_length: usize) -> String {
-    let original_len = content.len();
-    if start_index >= original_len {
+    let Some(chunk) = chunk(content, start_index, max_length) else {
         return "<error>No more content available.</error>".to_string();
-    }
-    let end = (start_index + max_length).min(original_len);
-    let mut slice = content[start_index..end].to_string();
-    if end < original_len {
+    };
+    let mut slice = chunk.text.to_string();
+    if let Some(next) = chunk.next_start() {
         slice.push_str(&format!(
             "\n\n<error>Content truncated. Call this tool again with start_index={} to get more.</error>",
-            end
+            next
         ));
     }
     slice
@@ -24,4 +57,15 @@ mod tests {
         assert!(truncate_with_hint(s, 10, 3).contains("No more content"));
         assert_eq!(truncate_with_hint(s, 0, 3)[..3], *"abc");
     }
+
+    #[test]
+    fn chunk_snaps_to_char_boundaries() {
+        let s = "aé€b";
+        let c = chunk(s, 0, 2).unwrap();
+        assert_eq!(c.text, "a");
+        assert_eq!(c.next_start(), Some(1));
+        let c = chunk(s, 2, 1).unwrap();
+        assert_eq!(c.text, "é");
+        assert_eq!(chunk(s, 6, 100).unwrap().next_start(), None);
+    }
 }
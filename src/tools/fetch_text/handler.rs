@@ -1,6 +1,8 @@
-use super::super::utils::{fetch_html_with_headers, required_str_arg, text_tool_result, FetchedResponse};
-use super::super::robots::Robots;
-use super::super::policy::{DomainPolicy, ensure_allowed};
+use super::super::utils::{execution_result, required_str_arg, structured_tool_result};
+use super::super::cache::CacheMode;
+use super::super::fetcher::{Fetcher, FetchedResponse, status_line};
+use super::super::progress;
+use super::super::pages::{PageStore, StoredPage};
 use super::content::{
     is_html_content_type, is_json_content_type, is_markdown_content_type, is_text_plain_content_type,
     is_probably_html, extract_title_or_h1, sanitize_html, pretty_json,
@@ -8,27 +10,49 @@ use super::content::{
 use super::extractors::{extract_best_blocks, extract_fallback_blocks};
 #[cfg(feature = "readability")]
 use super::extractors::extract_readability;
-use super::chunk::truncate_with_hint;
+use super::chunk::{chunk, truncate_with_hint};
 use async_trait::async_trait;
 use mcp_protocol_sdk::prelude::*;
-use reqwest::Client;
+use reqwest::StatusCode;
 use scraper::Html;
-use serde_json::Value;
+use serde_json::{Value, json};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::debug;
+#[cfg(feature = "readability")]
 use url::Url;
 
 pub struct FetchTextHandler {
-    pub client: Client,
-    pub robots: Arc<Robots>,
-    pub max_response_size: usize,
-    pub policy: Arc<DomainPolicy>,
+    pub fetcher: Fetcher,
+    /// Every extracted page is kept here and exposed as a `web://` resource.
+    pub pages: Arc<PageStore>,
+}
+
+pub(crate) struct FetchTextOptions {
+    url: String,
+    max_length: usize,
+    start_index: usize,
+    raw: bool,
+    format: String,
+    mode: String,
+    respect_robots: Option<bool>,
+    /// Return non-2xx pages instead of failing.
+    allow_error_status: bool,
+    cache: CacheMode,
 }
 
 #[async_trait]
 impl ToolHandler for FetchTextHandler {
     async fn call(&self, arguments: HashMap<String, Value>) -> McpResult<ToolResult> {
-        let url = required_str_arg(&arguments, "url")?;
+        let opts = FetchTextOptions::from_arguments(&arguments)?;
+        execution_result(self.run(opts).await)
+    }
+}
+
+impl FetchTextOptions {
+    pub(crate) fn from_arguments(arguments: &HashMap<String, Value>) -> McpResult<Self> {
+        let url = required_str_arg(arguments, "url")?;
         let max_length: usize = arguments
             .get("max_length")
             .and_then(|v| v.as_u64())
@@ -46,23 +70,45 @@ impl ToolHandler for FetchTextHandler {
         let format = arguments
             .get("format")
             .and_then(|v| v.as_str())
-            .unwrap_or("plain");
+            .unwrap_or("plain")
+            .to_string();
         let mode = arguments
             .get("mode")
             .and_then(|v| v.as_str())
-            .unwrap_or("auto");
-        let respect_robots_override = arguments
+            .unwrap_or("auto")
+            .to_string();
+        let respect_robots = arguments
             .get("respect_robots")
             .and_then(|v| v.as_bool());
+        let allow_error_status = arguments
+            .get("allow_error_status")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let cache = CacheMode::from_arguments(arguments)?;
+        Ok(Self { url, max_length, start_index, raw, format, mode, respect_robots, allow_error_status, cache })
+    }
+}
 
-        let parsed = Url::parse(&url).map_err(|e| McpError::validation(format!("Invalid url: {e}")))?;
-        ensure_allowed(&self.policy, &parsed)?;
-        let obey = respect_robots_override.unwrap_or(true);
-        if obey && !self.robots.allow(&parsed).await? {
-            return Err(McpError::validation("Blocked by robots.txt".to_string()));
-        }
+impl FetchTextHandler {
+    async fn run(&self, opts: FetchTextOptions) -> McpResult<ToolResult> {
+        let (start_index, max_length) = (opts.start_index, opts.max_length);
+        let page = self.extract(opts).await?;
+        Ok(page_result(&page, start_index, max_length))
+    }
 
-        let FetchedResponse { body, content_type } = fetch_html_with_headers(&self.client, &url, self.max_response_size).await?;
+    /// Fetches the page and extracts its full text, ignoring the paging options.
+    /// The result is also kept in the page store.
+    pub(crate) async fn extract(&self, opts: FetchTextOptions) -> McpResult<StoredPage> {
+        let FetchTextOptions { url, raw, format, mode, respect_robots, allow_error_status, cache, .. } = opts;
+        let mut response = self.fetcher.get(&url, respect_robots.unwrap_or(true), cache).await?;
+        if !allow_error_status {
+            response = response.error_for_status()?;
+        }
+        let FetchedResponse { status, body, content_type, final_url, redirects, elapsed, from_cache, .. } = response;
+        progress::step("Extracting content");
+        #[cfg(feature = "readability")]
+        let parsed = Url::parse(&final_url).map_err(|e| McpError::internal(e.to_string()))?;
+        let location = location_line(&final_url, &redirects, status);
 
         let ct_opt = content_type.as_deref();
         let is_html = is_html_content_type(ct_opt) || is_probably_html(&body);
@@ -70,58 +116,118 @@ impl ToolHandler for FetchTextHandler {
         let is_markdown = is_markdown_content_type(ct_opt);
         let is_text = is_text_plain_content_type(ct_opt);
 
-        if raw || mode.eq_ignore_ascii_case("raw") {
-            let text = truncate_with_hint(&body, start_index, max_length);
-            return Ok(text_tool_result(text));
-        }
-
-        if is_json {
-            let prefix = format!("URL: {}\n\n", url);
+        let mut title = None;
+        let mut mime_type = "text/plain";
+        let text = if raw || mode.eq_ignore_ascii_case("raw") {
+            debug!(url, "Returning raw body");
+            body
+        } else if is_json {
+            debug!(url, "Pretty-printing JSON body");
+            let prefix = format!("{location}\n\n");
             let content = pretty_json(&body).unwrap_or(body);
-            let text = prefix + &content;
-            return Ok(text_tool_result(truncate_with_hint(&text, start_index, max_length)));
+            prefix + &content
         } else if is_markdown || is_text {
-            let prefix = format!("URL: {}\n\n", url);
-            let text = prefix + &body;
-            return Ok(text_tool_result(truncate_with_hint(&text, start_index, max_length)));
+            debug!(url, content_type = ct_opt, "Returning text body unchanged");
+            if is_markdown {
+                mime_type = "text/markdown";
+            }
+            let prefix = format!("{location}\n\n");
+            prefix + &body
         } else if !is_html {
+            debug!(url, content_type = ct_opt, "Content type cannot be simplified");
             let ct_note = ct_opt.unwrap_or("");
             let prefix = if !ct_note.is_empty() {
                 format!("Content type {ct_note} cannot be simplified; returning raw content.\n\n")
             } else {
                 "Content cannot be simplified; returning raw content.\n\n".to_string()
             };
-            let text = prefix + &body;
-            return Ok(text_tool_result(truncate_with_hint(&text, start_index, max_length)));
+            prefix + &body
         } else if format.eq_ignore_ascii_case("markdown") {
+            debug!(url, "Converting HTML to markdown");
+            mime_type = "text/markdown";
             let doc = Html::parse_document(&body);
-            let prefix = if let Some(title) = extract_title_or_h1(&doc) {
-                format!("Title: {}\nURL: {}\n\n", title, url)
-            } else {
-                format!("URL: {}\n\n", url)
-            };
+            title = extract_title_or_h1(&doc);
+            let prefix = page_prefix(title.as_deref(), &location);
             let clean = sanitize_html(&body);
             let extracted = match htmd::convert(&clean) {
                 Ok(md) => md,
                 Err(_) => clean,
             };
-            let text = prefix + &extracted;
-            return Ok(text_tool_result(truncate_with_hint(&text, start_index, max_length)));
+            prefix + &extracted
         } else {
             let plain_html = if format.eq_ignore_ascii_case("plain") { sanitize_html(&body) } else { body.clone() };
             let doc = Html::parse_document(&plain_html);
-            let prefix = if let Some(title) = extract_title_or_h1(&doc) {
-                format!("Title: {}\nURL: {}\n\n", title, url)
-            } else {
-                format!("URL: {}\n\n", url)
-            };
+            title = extract_title_or_h1(&doc);
+            let prefix = page_prefix(title.as_deref(), &location);
+            debug!(url, mode = %mode, format = %format, "Extracting main content from HTML");
             let extracted = match mode.to_ascii_lowercase().as_str() {
                 #[cfg(feature = "readability")]
                 "readability" => extract_readability(&plain_html, &parsed).unwrap_or_else(|| extract_best_blocks(&doc).unwrap_or_else(|| extract_fallback_blocks(&doc)).join("\n")),
                 _ => extract_best_blocks(&doc).unwrap_or_else(|| extract_fallback_blocks(&doc)).join("\n"),
             };
-            let text = prefix + &extracted;
-            return Ok(text_tool_result(truncate_with_hint(&text, start_index, max_length)));
-        }
+            prefix + &extracted
+        };
+        let page = StoredPage {
+            url,
+            title,
+            content_type,
+            mime_type,
+            text,
+            final_url,
+            redirects,
+            status: status.as_u16(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            from_cache,
+            fetched_at: SystemTime::now(),
+        };
+        self.pages.insert(page.clone());
+        Ok(page)
+    }
+}
+
+fn page_prefix(title: Option<&str>, location: &str) -> String {
+    match title {
+        Some(title) => format!("Title: {}\n{}\n\n", title, location),
+        None => format!("{}\n\n", location),
+    }
+}
+
+/// `URL: ...` for the page header, followed by the redirect chain when there was one
+/// and the status when it was not a success.
+fn location_line(final_url: &str, redirects: &[String], status: StatusCode) -> String {
+    let mut line = format!("URL: {final_url}");
+    if !redirects.is_empty() {
+        line.push_str(&format!("\nRedirected: {} -> {final_url}", redirects.join(" -> ")));
     }
+    if !status.is_success() {
+        line.push_str(&format!("\nStatus: {}", status_line(status)));
+    }
+    line
+}
+
+fn page_result(page: &StoredPage, start_index: usize, max_length: usize) -> ToolResult {
+    let text = page.text.as_str();
+    let fallback = truncate_with_hint(text, start_index, max_length);
+    let (chunk_text, start, end, next) = match chunk(text, start_index, max_length) {
+        Some(c) => (c.text, c.start, c.end, c.next_start()),
+        None => ("", text.len(), text.len(), None),
+    };
+    structured_tool_result(
+        fallback,
+        json!({
+            "url": page.url,
+            "final_url": page.final_url,
+            "redirects": page.redirects,
+            "status": page.status,
+            "elapsed_ms": page.elapsed_ms,
+            "from_cache": page.from_cache,
+            "title": page.title,
+            "content_type": page.content_type,
+            "text": chunk_text,
+            "start_index": start,
+            "end_index": end,
+            "total_length": text.len(),
+            "next_start_index": next,
+        }),
+    )
 }
@@ -1,5 +1,6 @@
 use std::sync::OnceLock;
-use super::super::meta::{ToolInputSchema, ToolMeta};
+use super::super::cache::cache_arg_schema;
+use super::super::meta::{ToolAnnotations, ToolInputSchema, ToolMeta};
 
 static META: OnceLock<ToolMeta> = OnceLock::new();
 
@@ -15,7 +16,9 @@ pub fn meta() -> ToolMeta {
                     "raw": { "type": "boolean" },
                     "format": { "type": "string", "enum": ["plain", "markdown"], "default": "plain" },
                     "mode": { "type": "string", "enum": ["auto", "best_blocks", "readability", "raw"], "default": "auto" },
-                    "respect_robots": { "type": ["boolean", "null"], "description": "Override robots behavior for this call" }
+                    "respect_robots": { "type": ["boolean", "null"], "description": "Override robots behavior for this call" },
+                    "allow_error_status": { "type": "boolean", "default": false, "description": "Return the page even when the server answers with a non-2xx status (e.g. to inspect an error page)" },
+                    "cache": cache_arg_schema()
                 }),
                 vec!["url".to_string()],
             );
@@ -25,6 +28,26 @@ pub fn meta() -> ToolMeta {
                 "Fetches the text content of a URL (optionally chunked and as raw HTML)",
                 schema,
             )
+            .with_output_schema(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string" },
+                    "final_url": { "type": "string", "description": "The URL that answered, after redirects" },
+                    "redirects": { "type": "array", "items": { "type": "string" }, "description": "URLs that redirected, in order, starting with url" },
+                    "status": { "type": "integer", "description": "HTTP status of the final response" },
+                    "elapsed_ms": { "type": "integer", "description": "Time taken by the fetch, redirects included" },
+                    "from_cache": { "type": "boolean", "description": "Whether the page was served from the HTTP cache" },
+                    "title": { "type": ["string", "null"] },
+                    "content_type": { "type": ["string", "null"] },
+                    "text": { "type": "string", "description": "The requested chunk of extracted content" },
+                    "start_index": { "type": "integer" },
+                    "end_index": { "type": "integer" },
+                    "total_length": { "type": "integer" },
+                    "next_start_index": { "type": ["integer", "null"], "description": "Pass as start_index to fetch the next chunk; null when complete" }
+                },
+                "required": ["url", "final_url", "redirects", "status", "elapsed_ms", "from_cache", "text", "start_index", "end_index", "total_length", "next_start_index"]
+            }))
+            .with_annotations(ToolAnnotations::read_only_web())
         })
         .clone()
 }
@@ -0,0 +1,393 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use mcp_protocol_sdk::prelude::*;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use scraper::Html;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use super::cache::{CacheMode, Cached, HttpCache};
+use super::fetch_text::content::{is_html_content_type, is_probably_html, normalize_space, sanitize_html};
+use super::net::HttpClient;
+use super::policy::{DomainPolicy, ensure_allowed};
+use super::progress;
+use super::redirect::{self, Followed, HopCheck};
+use super::robots::Robots;
+use super::utils::decode_body;
+
+/// Sent with every fetch; part of the cache key, since a server may answer differently
+/// for another `Accept`.
+const ACCEPT_HTML: &str = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+
+/// Fetches pages for the tools. Every fetch goes through the same checks: the domain
+/// policy, robots.txt (unless the call opts out), the address filter and the size
+/// limit, on the requested URL and on every redirect. Successful responses are kept in
+/// the HTTP cache and reused as their caching headers allow.
+#[derive(Clone)]
+pub struct Fetcher {
+    client: HttpClient,
+    policy: Arc<DomainPolicy>,
+    robots: Arc<Robots>,
+    cache: Arc<HttpCache>,
+    max_response_size: usize,
+}
+
+/// A fetched page with the details of the response that delivered it.
+#[derive(Debug, Clone)]
+pub struct FetchedResponse {
+    pub status: StatusCode,
+    /// The URL that answered, after redirects.
+    pub final_url: String,
+    /// URLs that answered with a redirect, starting with the requested one; empty
+    /// when the first response was the final one.
+    pub redirects: Vec<String>,
+    pub headers: HeaderMap,
+    pub content_type: Option<String>,
+    /// From sending the request to the end of the body, redirects included.
+    pub elapsed: Duration,
+    /// The body came from the HTTP cache, either fresh or confirmed by a 304.
+    pub from_cache: bool,
+    /// The body decoded with the charset from `Content-Type`, or a detected one.
+    pub body: String,
+}
+
+/// Cache validators from an earlier response, sent back as `If-None-Match` / `If-Modified-Since`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub enum Conditional {
+    NotModified,
+    Modified(Box<FetchedResponse>, Validators),
+}
+
+/// Characters of an error page quoted in the error for a non-2xx response.
+const ERROR_EXCERPT_LEN: usize = 300;
+
+impl FetchedResponse {
+    /// Turns a non-2xx response into an error with the status, the reason and the start
+    /// of the page's text, so an error page is not mistaken for the content.
+    pub fn error_for_status(self) -> McpResult<Self> {
+        if self.status.is_success() {
+            return Ok(self);
+        }
+        let mut message = format!("HTTP {} from {}", status_line(self.status), self.final_url);
+        let excerpt = self.excerpt(ERROR_EXCERPT_LEN);
+        if !excerpt.is_empty() {
+            message.push_str(&format!("\n\n{excerpt}"));
+        }
+        message.push_str("\n\n(Pass allow_error_status: true to return the page anyway.)");
+        info!(url = %self.final_url, status = self.status.as_u16(), "Non-success HTTP status");
+        Err(McpError::internal(message))
+    }
+
+    /// The beginning of the body as plain text, whitespace collapsed.
+    fn excerpt(&self, max_chars: usize) -> String {
+        let text = if is_html_content_type(self.content_type.as_deref()) || is_probably_html(&self.body) {
+            let doc = Html::parse_document(&sanitize_html(&self.body));
+            normalize_space(&doc.root_element().text().collect::<Vec<_>>().join(" "))
+        } else {
+            normalize_space(&self.body)
+        };
+        match text.char_indices().nth(max_chars) {
+            Some((end, _)) => format!("{}…", &text[..end]),
+            None => text,
+        }
+    }
+}
+
+/// `404 Not Found`, or just the code for statuses without a standard reason.
+pub fn status_line(status: StatusCode) -> String {
+    match status.canonical_reason() {
+        Some(reason) => format!("{} {reason}", status.as_u16()),
+        None => status.as_u16().to_string(),
+    }
+}
+
+impl Fetcher {
+    pub fn new(
+        client: HttpClient,
+        policy: Arc<DomainPolicy>,
+        robots: Arc<Robots>,
+        cache: Arc<HttpCache>,
+        max_response_size: usize,
+    ) -> Self {
+        Self { client, policy, robots, cache, max_response_size }
+    }
+
+    pub fn policy(&self) -> &DomainPolicy {
+        &self.policy
+    }
+
+    /// GETs `url` after checking it against the domain policy and, when `obey_robots`
+    /// is set, robots.txt. Any status is returned; see `FetchedResponse::error_for_status`.
+    pub async fn get(&self, url: &str, obey_robots: bool, mode: CacheMode) -> McpResult<FetchedResponse> {
+        let hops = self.check(url, obey_robots).await?;
+        self.get_cached(url, url, mode, hops).await
+    }
+
+    /// GETs an API URL the server built itself, so neither the domain policy nor
+    /// robots.txt apply. `public_url` stands for `url` in the cache, in logs and in errors,
+    /// and must leave out secrets such as API keys.
+    pub async fn get_api(&self, url: &str, public_url: &str, mode: CacheMode) -> McpResult<FetchedResponse> {
+        self.get_cached(url, public_url, mode, HopCheck::default()).await.map_err(|e| match e {
+            McpError::Internal(msg) => McpError::internal(msg.replace(url, public_url)),
+            McpError::Validation(msg) => McpError::validation(msg.replace(url, public_url)),
+            other => other,
+        })
+    }
+
+    async fn get_cached(
+        &self,
+        url: &str,
+        public_url: &str,
+        mode: CacheMode,
+        hops: HopCheck<'_>,
+    ) -> McpResult<FetchedResponse> {
+        let started = Instant::now();
+        let key = format!("{ACCEPT_HTML} {public_url}");
+        let cached = self.cache.get(&key).await;
+        if let Some(cached) = &cached {
+            let usable = match mode {
+                CacheMode::Default => cached.fresh,
+                CacheMode::NoCache => false,
+                CacheMode::OnlyIfCached => cached.fresh || !cached.must_revalidate,
+            };
+            if usable {
+                debug!(url = public_url, fresh = cached.fresh, "Using cached response");
+                // The policy may have changed since the redirects were followed.
+                for hop in cached.redirects.iter().skip(1).chain(std::iter::once(&cached.final_url)) {
+                    if let Ok(hop) = Url::parse(hop) {
+                        hops.check(&hop).await?;
+                    }
+                }
+                return Ok(from_cache(cached, started));
+            }
+        }
+        if mode == CacheMode::OnlyIfCached {
+            return Err(McpError::validation(format!(
+                "{public_url} is not in the cache (or needs revalidation) and cache is only-if-cached"
+            )));
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(ACCEPT_HTML));
+        if let Some(cached) = &cached {
+            headers.extend(cached.validators());
+        }
+        let mut raw = self.send_raw(url, public_url, headers, hops).await?;
+        for hop in std::iter::once(&mut raw.final_url).chain(&mut raw.redirects) {
+            if hop == url {
+                *hop = public_url.to_string();
+            }
+        }
+        if raw.status == StatusCode::NOT_MODIFIED
+            && let Some(cached) = cached
+        {
+            debug!(url = public_url, "Cached response revalidated");
+            let refreshed = self.cache.refresh(&key, &raw.headers).await.unwrap_or(cached);
+            return Ok(from_cache(&refreshed, started));
+        }
+        if raw.status == StatusCode::OK {
+            self.cache.put(&key, &raw.final_url, &raw.redirects, &raw.headers, &raw.bytes).await;
+        }
+        Ok(raw.decode(started))
+    }
+
+    /// Runs the domain policy and robots.txt checks a fetch of `url` would, without fetching it.
+    pub async fn ensure_permitted(&self, url: &str) -> McpResult<()> {
+        self.check(url, true).await.map(|_| ())
+    }
+
+    /// Conditional GET used to re-check a page: `NotModified` on a 304, otherwise the
+    /// page together with the validators to send next time.
+    pub async fn get_if_modified(&self, url: &str, validators: &Validators) -> McpResult<Conditional> {
+        let hops = self.check(url, true).await?;
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = validators.etag.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = validators.last_modified.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        let resp = self.send_raw(url, url, headers, hops).await?.decode(Instant::now());
+        debug!(url, status = resp.status.as_u16(), "Conditional request");
+        if resp.status == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+        if !resp.status.is_success() {
+            return Err(McpError::internal(format!("HTTP {} from {url}", resp.status)));
+        }
+        let header = |name| resp.headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let next = Validators { etag: header(ETAG), last_modified: header(LAST_MODIFIED) };
+        Ok(Conditional::Modified(Box::new(resp), next))
+    }
+
+    /// Checks the requested URL and returns the checks its redirects have to pass.
+    async fn check(&self, url: &str, obey_robots: bool) -> McpResult<HopCheck<'_>> {
+        let parsed = Url::parse(url).map_err(|e| McpError::validation(format!("Invalid url: {e}")))?;
+        ensure_allowed(&self.policy, &parsed).await?;
+        if obey_robots {
+            progress::step("Checking robots.txt");
+            if !self.robots.allow(&parsed).await? {
+                info!(url, "Blocked by robots.txt");
+                return Err(McpError::validation("Blocked by robots.txt".to_string()));
+            }
+        }
+        Ok(HopCheck { policy: Some(&self.policy), robots: obey_robots.then_some(&*self.robots) })
+    }
+
+    /// `label` is the URL as it may appear in logs.
+    async fn send_raw(&self, url: &str, label: &str, headers: HeaderMap, hops: HopCheck<'_>) -> McpResult<RawResponse> {
+        debug!(url = label, "Fetching");
+        let Followed { response, url: final_url, redirects } = redirect::get(&self.client, url, headers, hops).await?;
+        let status = response.status();
+        debug!(url = label, status = status.as_u16(), content_length = ?response.content_length(), "Received response");
+        if !redirects.is_empty() {
+            info!(url = label, %final_url, hops = redirects.len(), "Followed redirects");
+        }
+        let headers = response.headers().clone();
+        let bytes = read_limited(response, label, self.max_response_size).await?;
+        Ok(RawResponse {
+            status,
+            final_url: final_url.into(),
+            redirects: redirects.into_iter().map(Url::into).collect(),
+            headers,
+            bytes,
+        })
+    }
+}
+
+/// A response as received, before the body is decoded.
+struct RawResponse {
+    status: StatusCode,
+    final_url: String,
+    redirects: Vec<String>,
+    headers: HeaderMap,
+    bytes: Vec<u8>,
+}
+
+impl RawResponse {
+    fn decode(self, started: Instant) -> FetchedResponse {
+        let content_type = content_type_of(&self.headers);
+        let body = decode_body(&self.bytes, content_type.as_deref());
+        let elapsed = started.elapsed();
+        debug!(url = %self.final_url, elapsed_ms = elapsed.as_millis() as u64, "Fetch complete");
+        FetchedResponse {
+            status: self.status,
+            final_url: self.final_url,
+            redirects: self.redirects,
+            headers: self.headers,
+            content_type,
+            elapsed,
+            from_cache: false,
+            body,
+        }
+    }
+}
+
+/// Only `200 OK` responses are stored, so that is the status of every cached one.
+fn from_cache(cached: &Cached, started: Instant) -> FetchedResponse {
+    let content_type = content_type_of(&cached.headers);
+    FetchedResponse {
+        status: StatusCode::OK,
+        final_url: cached.final_url.clone(),
+        redirects: cached.redirects.clone(),
+        headers: cached.headers.clone(),
+        body: decode_body(&cached.body, content_type.as_deref()),
+        content_type,
+        elapsed: started.elapsed(),
+        from_cache: true,
+    }
+}
+
+fn content_type_of(headers: &HeaderMap) -> Option<String> {
+    headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(str::to_string)
+}
+
+/// Streams the body, enforcing `max_response_size` and reporting download progress.
+async fn read_limited(resp: reqwest::Response, url: &str, max_response_size: usize) -> McpResult<Vec<u8>> {
+    if let Some(len) = resp.content_length()
+        && (len as usize > max_response_size)
+    {
+        warn!(url, len, max_response_size, "Response too large");
+        return Err(McpError::validation(format!(
+            "Response too large: {len} bytes (max {max_response_size})"
+        )));
+    }
+
+    let expected = resp.content_length();
+    let mut total: usize = 0;
+    let mut reported: usize = 0;
+    let mut out = Vec::with_capacity(64 * 1024);
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk_res) = stream.next().await {
+        let chunk = chunk_res.map_err(|e| McpError::internal(e.to_string()))?;
+        total = total.saturating_add(chunk.len());
+        if total > max_response_size {
+            warn!(url, max_response_size, "Response exceeded size limit while streaming");
+            return Err(McpError::validation(format!(
+                "Response exceeded limit ({max_response_size} bytes)"
+            )));
+        }
+        out.extend_from_slice(&chunk);
+        if total - reported >= PROGRESS_STEP_BYTES {
+            report_download(total, expected);
+            reported = total;
+        }
+    }
+    report_download(total, expected);
+    debug!(url, bytes = total, "Download complete");
+    Ok(out)
+}
+
+const PROGRESS_STEP_BYTES: usize = 64 * 1024;
+
+fn report_download(total: usize, expected: Option<u64>) {
+    progress::report(
+        total as f64,
+        expected.map(|len| len as f64),
+        format!("Downloaded {total} bytes"),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, content_type: &str, body: &str) -> FetchedResponse {
+        FetchedResponse {
+            status: StatusCode::from_u16(status).unwrap(),
+            final_url: "https://example.com/missing".to_string(),
+            redirects: Vec::new(),
+            headers: HeaderMap::new(),
+            content_type: Some(content_type.to_string()),
+            elapsed: Duration::ZERO,
+            from_cache: false,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn error_for_status_quotes_the_error_page() {
+        assert!(response(200, "text/html", "<p>ok</p>").error_for_status().is_ok());
+
+        let page = "<html><head><style>p{}</style></head><body><h1>Not   here</h1><p>Try the index.</p></body></html>";
+        let Err(McpError::Internal(message)) = response(404, "text/html", page).error_for_status() else {
+            panic!("404 should be an error");
+        };
+        assert!(message.starts_with("HTTP 404 Not Found from https://example.com/missing\n\nNot here Try the index."));
+        assert!(!message.contains("p{}"));
+
+        let long = "x".repeat(ERROR_EXCERPT_LEN + 50);
+        let Err(McpError::Internal(message)) = response(500, "text/plain", &long).error_for_status() else {
+            panic!("500 should be an error");
+        };
+        assert!(message.contains(&format!("{}…", "x".repeat(ERROR_EXCERPT_LEN))));
+    }
+}
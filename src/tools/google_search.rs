@@ -1,15 +1,17 @@
 use async_trait::async_trait;
 use mcp_protocol_sdk::prelude::*;
-use reqwest::Client;
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::OnceLock;
+use url::Url;
 
 use crate::config::Config;
 use crate::environment::{get_google_api_key, get_google_cse_id};
-use super::meta::{ToolInputSchema, ToolMeta};
-use super::utils::{required_str_arg, text_tool_result};
+use super::cache::{CacheMode, cache_arg_schema};
+use super::fetcher::Fetcher;
+use super::meta::{ToolAnnotations, ToolInputSchema, ToolMeta};
+use super::utils::{execution_result, required_str_arg, structured_tool_result};
 
 static META: OnceLock<ToolMeta> = OnceLock::new();
 
@@ -19,7 +21,8 @@ pub fn meta() -> ToolMeta {
             "query": { "type": "string", "description": "The search query (Google)." },
             "num": { "type": "integer", "minimum": 1, "maximum": 10, "default": 5, "description": "Number of results to return (1-10)." },
             "site": { "type": "string", "description": "Optional site/domain to restrict results, e.g., 'example.com'." },
-            "format": { "type": "string", "enum": ["text", "json"], "default": "text" }
+            "format": { "type": "string", "enum": ["text", "json"], "default": "text" },
+            "cache": cache_arg_schema()
         });
         let schema = ToolInputSchema::new("object", properties, vec!["query".to_string()]);
         ToolMeta::new(
@@ -28,12 +31,33 @@ pub fn meta() -> ToolMeta {
             "Search the web using Google Programmable Search (Custom Search API).",
             schema,
         )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "results": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "title": { "type": ["string", "null"] },
+                            "link": { "type": ["string", "null"] },
+                            "snippet": { "type": ["string", "null"] }
+                        }
+                    }
+                }
+            },
+            "required": ["query", "results"]
+        }))
+        .with_annotations(ToolAnnotations::read_only_web())
     })
     .clone()
 }
 
+const SEARCH_ENDPOINT: &str = "https://www.googleapis.com/customsearch/v1";
+
 pub struct GoogleSearchHandler {
-    pub client: Client,
+    pub fetcher: Fetcher,
     pub api_key: Option<String>,
     pub cse_id: Option<String>,
 }
@@ -57,7 +81,14 @@ impl ToolHandler for GoogleSearchHandler {
         let num = arguments.get("num").and_then(|v| v.as_u64()).unwrap_or(5).min(10) as u8;
         let site = arguments.get("site").and_then(|v| v.as_str()).map(|s| s.trim()).filter(|s| !s.is_empty());
         let format = arguments.get("format").and_then(|v| v.as_str()).unwrap_or("text");
+        let cache = CacheMode::from_arguments(&arguments)?;
+
+        execution_result(self.run(&query, num, site, format, cache).await)
+    }
+}
 
+impl GoogleSearchHandler {
+    async fn run(&self, query: &str, num: u8, site: Option<&str>, format: &str, cache: CacheMode) -> McpResult<ToolResult> {
         let api_key = self.api_key.clone()
             .ok_or_else(|| McpError::validation("Google API key not configured (set in config or GOOGLE_API_KEY env)".to_string()))?;
         let cse_id = self.cse_id.clone()
@@ -67,62 +98,50 @@ impl ToolHandler for GoogleSearchHandler {
 
         let num_s = num.to_string();
 
-        let resp = self.client
-            .get("https://www.googleapis.com/customsearch/v1")
-            .query(&[
-                ("key", api_key.as_str()),
-                ("cx", cse_id.as_str()),
-                ("q", q.as_str()),
-                ("num", num_s.as_str()),
-            ])
-            .send()
-            .await
+        let params = [("cx", cse_id.as_str()), ("q", q.as_str()), ("num", num_s.as_str())];
+        // The API key stays out of the cache key, which is written to disk.
+        let cache_key = Url::parse_with_params(SEARCH_ENDPOINT, params).map_err(|e| McpError::internal(e.to_string()))?;
+        let url = Url::parse_with_params(SEARCH_ENDPOINT, [("key", api_key.as_str())].into_iter().chain(params))
             .map_err(|e| McpError::internal(e.to_string()))?;
+        let resp = self.fetcher.get_api(url.as_str(), cache_key.as_str(), cache).await?;
 
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            return Err(McpError::internal(format!("Google API error: {status} - {body}")));
+        if !resp.status.is_success() {
+            return Err(McpError::internal(format!("Google API error: {} - {}", resp.status, resp.body)));
         }
 
-        let data: GoogleSearchResponse = resp.json().await.map_err(|e| McpError::internal(e.to_string()))?;
+        let data: GoogleSearchResponse = serde_json::from_str(&resp.body).map_err(|e| McpError::internal(e.to_string()))?;
         let items = data.items.unwrap_or_default();
 
-        match format {
-            "json" => {
-                let out = serde_json::json!({
-                    "results": items.iter().map(|it| serde_json::json!({
-                        "title": it.title,
-                        "link": it.link,
-                        "snippet": it.snippet,
-                    })).collect::<Vec<_>>()
-                });
-                Ok(text_tool_result(out.to_string()))
-            }
-            _ => {
-                if items.is_empty() {
-                    return Ok(text_tool_result("No results."));
-                }
-                let text_lines: Vec<String> = items
-                    .iter()
-                    .enumerate()
-                    .map(|(i, it)| {
-                        let title = it.title.as_deref().unwrap_or("");
-                        let link = it.link.as_deref().unwrap_or("");
-                        let snippet = it.snippet.as_deref().unwrap_or("");
-                        format!("{}. {}\n{}\n{}\n", i + 1, title, link, snippet)
-                    })
-                    .collect();
-                Ok(text_tool_result(text_lines.join("\n")))
-            }
-        }
+        let structured = serde_json::json!({
+            "query": query,
+            "results": items.iter().map(|it| serde_json::json!({
+                "title": it.title,
+                "link": it.link,
+                "snippet": it.snippet,
+            })).collect::<Vec<_>>()
+        });
+
+        let text = match format {
+            "json" => serde_json::json!({ "results": structured["results"] }).to_string(),
+            _ if items.is_empty() => "No results.".to_string(),
+            _ => items
+                .iter()
+                .enumerate()
+                .map(|(i, it)| {
+                    let title = it.title.as_deref().unwrap_or("");
+                    let link = it.link.as_deref().unwrap_or("");
+                    let snippet = it.snippet.as_deref().unwrap_or("");
+                    format!("{}. {}\n{}\n{}\n", i + 1, title, link, snippet)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        Ok(structured_tool_result(text, structured))
     }
-}
 
-impl GoogleSearchHandler {
-    pub fn from_config(client: Client, cfg: &Config) -> Self {
+    pub fn from_config(fetcher: Fetcher, cfg: &Config) -> Self {
         let api_key = get_google_api_key(cfg);
         let cse_id = get_google_cse_id(cfg);
-        Self { client, api_key, cse_id }
+        Self { fetcher, api_key, cse_id }
     }
 }
@@ -1,6 +1,8 @@
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::config::ToolAnnotationsConfig;
+
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolInputSchema {
@@ -30,6 +32,40 @@ impl Default for ToolInputSchema {
     }
 }
 
+/// MCP tool annotations: hints clients use to decide, e.g., whether a call may be auto-approved.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+    /// Hints for tools that only read from the web and never change anything.
+    pub fn read_only_web() -> Self {
+        Self {
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(true),
+        }
+    }
+
+    pub fn apply(&mut self, overrides: &ToolAnnotationsConfig) {
+        let ToolAnnotationsConfig { read_only_hint, destructive_hint, idempotent_hint, open_world_hint } = overrides;
+        self.read_only_hint = read_only_hint.or(self.read_only_hint);
+        self.destructive_hint = destructive_hint.or(self.destructive_hint);
+        self.idempotent_hint = idempotent_hint.or(self.idempotent_hint);
+        self.open_world_hint = open_world_hint.or(self.open_world_hint);
+    }
+}
+
 #[derive(Serialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolMeta {
@@ -37,6 +73,10 @@ pub struct ToolMeta {
     pub title: String,
     pub description: String,
     pub input_schema: ToolInputSchema,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
 }
 
 #[derive(Serialize, Default, Clone)]
@@ -50,6 +90,8 @@ impl ToolMeta {
             title: title.to_string(),
             description: description.to_string(),
             input_schema: ToolInputSchema::default(),
+            output_schema: None,
+            annotations: None,
         }
     }
     pub fn new(
@@ -63,6 +105,18 @@ impl ToolMeta {
             title: title.to_string(),
             description: description.to_string(),
             input_schema,
+            output_schema: None,
+            annotations: None,
         }
     }
+
+    pub fn with_output_schema(mut self, output_schema: Value) -> Self {
+        self.output_schema = Some(output_schema);
+        self
+    }
+
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
 }
@@ -0,0 +1,254 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use reqwest::Client;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use tracing::warn;
+
+use crate::config::NetworkConfig;
+
+/// The shared HTTP client with the filter its resolver applies. Literal IP hosts never
+/// reach the resolver, so `tools::redirect` checks them against `ips` instead.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    pub client: Client,
+    pub ips: Arc<IpFilter>,
+}
+
+/// Decides which addresses the server may connect to: global unicast addresses, plus
+/// the `[network] allow_cidrs` ranges, minus the `deny_cidrs` ranges.
+#[derive(Debug, Default)]
+pub struct IpFilter {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+}
+
+impl IpFilter {
+    pub fn from_config(cfg: &NetworkConfig) -> Result<Self, String> {
+        let parse = |list: &[String]| list.iter().map(|s| s.parse::<Cidr>()).collect::<Result<Vec<_>, _>>();
+        Ok(Self { allow: parse(&cfg.allow_cidrs)?, deny: parse(&cfg.deny_cidrs)? })
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        if self.deny.iter().any(|c| c.contains(ip)) {
+            return false;
+        }
+        is_global_ip(ip) || self.allow.iter().any(|c| c.contains(ip))
+    }
+}
+
+/// DNS resolver for the HTTP client that drops addresses the filter refuses, so a
+/// hostname pointing at 127.0.0.1 or 169.254.169.254 cannot be reached, whether it is
+/// the requested URL or a redirect target.
+pub struct FilteringResolver {
+    filter: Arc<IpFilter>,
+}
+
+impl FilteringResolver {
+    pub fn new(filter: Arc<IpFilter>) -> Self {
+        Self { filter }
+    }
+}
+
+impl Resolve for FilteringResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let filter = self.filter.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let resolved: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let (allowed, blocked): (Vec<_>, Vec<_>) = resolved.into_iter().partition(|a| filter.allows(a.ip()));
+            if allowed.is_empty() {
+                let ips: Vec<_> = blocked.iter().map(|a| a.ip().to_string()).collect();
+                warn!(host, ips = %ips.join(", "), "Blocked connection to non-global address");
+                let message = format!("{host} resolves to a non-global address (blocked)");
+                return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, message).into());
+            }
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// An address range such as `10.0.0.0/8` or `fd00::/8`; a bare address is a single host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl std::str::FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let network = addr
+            .parse::<IpAddr>()
+            .map_err(|_| format!("Invalid CIDR '{s}': '{addr}' is not an IP address"))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("Invalid CIDR '{s}': prefix must be 0-{max}"))?,
+            None => max,
+        };
+        // `::ffff:10.0.0.0/104` is stored as `10.0.0.0/8`, since addresses are compared in canonical form.
+        let (network, prefix) = match (network, canonical(network)) {
+            (IpAddr::V6(_), v4 @ IpAddr::V4(_)) if prefix >= 96 => (v4, prefix - 96),
+            _ => (network, prefix),
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_matches(u32::from(net).into(), u32::from(ip).into(), self.prefix, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => prefix_matches(u128::from(net), u128::from(ip), self.prefix, 128),
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, prefix: u8, bits: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix;
+    (net >> shift) == (ip >> shift)
+}
+
+/// IPv6 forms that carry an IPv4 address (IPv4-mapped `::ffff:a.b.c.d` and the NAT64
+/// prefix `64:ff9b::/96`) are judged by that IPv4 address.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return IpAddr::V4(v4);
+            }
+            let s = v6.segments();
+            if s[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                return IpAddr::V4(Ipv4Addr::from(u128::from(v6) as u32));
+            }
+            ip
+        }
+        v4 => v4,
+    }
+}
+
+pub fn is_global_ip(ip: IpAddr) -> bool {
+    match canonical(ip) {
+        IpAddr::V4(v4) => is_global_ipv4(v4),
+        IpAddr::V6(v6) => is_global_ipv6(v6),
+    }
+}
+
+fn is_global_ipv4(ip: Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    // 10.0.0.0/8
+    if octets[0] == 10 { return false; }
+    // 172.16.0.0/12
+    if octets[0] == 172 && (16..=31).contains(&octets[1]) { return false; }
+    // 192.168.0.0/16
+    if octets[0] == 192 && octets[1] == 168 { return false; }
+    // 127.0.0.0/8 loopback
+    if octets[0] == 127 { return false; }
+    // 169.254.0.0/16 link-local (includes cloud metadata at 169.254.169.254)
+    if octets[0] == 169 && octets[1] == 254 { return false; }
+    // 100.64.0.0/10 carrier-grade NAT
+    if octets[0] == 100 && (64..=127).contains(&octets[1]) { return false; }
+    // 192.0.0.0/24 IETF protocol assignments, 192.0.2.0/24, 198.51.100.0/24, 203.0.113.0/24 documentation
+    if octets[..3] == [192, 0, 0] || octets[..3] == [192, 0, 2] { return false; }
+    if octets[..3] == [198, 51, 100] || octets[..3] == [203, 0, 113] { return false; }
+    // 198.18.0.0/15 benchmarking
+    if octets[0] == 198 && (18..=19).contains(&octets[1]) { return false; }
+    // 0.0.0.0/8, 255.255.255.255 broadcast
+    if octets[0] == 0 || ip == Ipv4Addr::new(255,255,255,255) { return false; }
+    // 224.0.0.0/4 multicast & 240.0.0.0/4 reserved
+    if (224..=255).contains(&octets[0]) { return false; }
+    true
+}
+
+fn is_global_ipv6(ip: Ipv6Addr) -> bool {
+    let s = ip.segments();
+    let seg0 = s[0];
+    let embedded = |hi: u16, lo: u16| Ipv4Addr::from(((hi as u32) << 16) | lo as u32);
+    // ::/128 unspecified
+    if ip.is_unspecified() { return false; }
+    // ::1/128 loopback
+    if ip.is_loopback() { return false; }
+    // ::/96 deprecated IPv4-compatible addresses carry an IPv4 address in the last 32 bits
+    if s[..6] == [0; 6] {
+        return is_global_ipv4(embedded(s[6], s[7]));
+    }
+    // 100::/64 discard-only
+    if s[..4] == [0x100, 0, 0, 0] { return false; }
+    // fe80::/10 link-local
+    if (seg0 & 0xffc0) == 0xfe80 { return false; }
+    // fec0::/10 deprecated site-local
+    if (seg0 & 0xffc0) == 0xfec0 { return false; }
+    // fc00::/7 unique local
+    if (seg0 & 0xfe00) == 0xfc00 { return false; }
+    // ff00::/8 multicast
+    if (seg0 & 0xff00) == 0xff00 { return false; }
+    // 2001:db8::/32 documentation
+    if seg0 == 0x2001 && s[1] == 0x0db8 { return false; }
+    // 2001::/32 Teredo embeds the server's IPv4 address and the client's, inverted
+    if seg0 == 0x2001 && s[1] == 0 {
+        return is_global_ipv4(embedded(s[2], s[3])) && is_global_ipv4(embedded(!s[6], !s[7]));
+    }
+    // 64:ff9b:1::/48 local-use NAT64
+    if s[..3] == [0x64, 0xff9b, 1] { return false; }
+    // 2002::/16 6to4 embeds an IPv4 address in the next 32 bits
+    if seg0 == 0x2002 {
+        return is_global_ipv4(embedded(s[1], s[2]));
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn filter_handles_mapped_addresses_and_cidr_lists() {
+        let default = IpFilter::default();
+        assert!(default.allows(ip("93.184.216.34")));
+        assert!(!default.allows(ip("169.254.169.254")));
+        assert!(!default.allows(ip("::ffff:127.0.0.1")));
+        assert!(!default.allows(ip("64:ff9b::a9fe:a9fe")));
+        assert!(!default.allows(ip("2002:7f00:1::1")));
+        assert!(!default.allows(ip("::7f00:1")));
+        assert!(default.allows(ip("::5db8:d822")));
+        assert!(!default.allows(ip("100::1")));
+        // Teredo with a global server and the client 127.0.0.1 (stored inverted)
+        assert!(!default.allows(ip("2001:0:5db8:d822::80ff:fffe")));
+        assert!(!default.allows(ip("2001:0:a00:1::a247:2ddd")));
+        assert!(default.allows(ip("2001:0:5db8:d822::a247:2ddd")));
+        assert!(default.allows(ip("64:ff9b::5db8:d822")));
+
+        let cfg = NetworkConfig {
+            allow_cidrs: vec!["10.1.0.0/16".into(), "fd00::/8".into()],
+            deny_cidrs: vec!["10.1.2.0/24".into(), "93.184.216.34".into()],
+        };
+        let filter = IpFilter::from_config(&cfg).unwrap();
+        assert!(filter.allows(ip("10.1.5.5")));
+        assert!(filter.allows(ip("::ffff:10.1.5.5")));
+        assert!(!filter.allows(ip("10.1.2.3")));
+        assert!(!filter.allows(ip("10.2.0.1")));
+        assert!(filter.allows(ip("fd12::1")));
+        assert!(!filter.allows(ip("93.184.216.34")));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Scheme used to expose fetched pages as MCP resources: `web://https://example.com/`.
+pub const RESOURCE_SCHEME: &str = "web://";
+
+const MAX_PAGES: usize = 50;
+
+/// A page as last extracted by `fetch_url_text`.
+#[derive(Debug, Clone)]
+pub struct StoredPage {
+    pub url: String,
+    pub title: Option<String>,
+    pub content_type: Option<String>,
+    /// `text/markdown` or `text/plain`, depending on how the text was extracted.
+    pub mime_type: &'static str,
+    pub text: String,
+    /// Where the page was fetched from after redirects; the same as `url` without them.
+    pub final_url: String,
+    /// URLs that redirected to `final_url`, starting with `url`.
+    pub redirects: Vec<String>,
+    /// HTTP status of the final response; only a success unless the call allowed errors.
+    pub status: u16,
+    /// How long the fetch took, redirects included.
+    pub elapsed_ms: u64,
+    /// The body came from the HTTP cache.
+    pub from_cache: bool,
+    pub fetched_at: SystemTime,
+}
+
+impl StoredPage {
+    pub fn uri(&self) -> String {
+        resource_uri(&self.url)
+    }
+}
+
+/// Recently fetched pages, most recent last. Shared by the tools and the
+/// `resources/*` handlers and kept across config reloads.
+#[derive(Debug, Default)]
+pub struct PageStore {
+    pages: Mutex<VecDeque<StoredPage>>,
+}
+
+impl PageStore {
+    /// Stores `page`, replacing an earlier copy of the same URL and evicting the
+    /// oldest page once the store is full.
+    pub fn insert(&self, page: StoredPage) {
+        let mut pages = self.pages.lock().unwrap();
+        pages.retain(|p| p.url != page.url);
+        if pages.len() >= MAX_PAGES {
+            pages.pop_front();
+        }
+        pages.push_back(page);
+    }
+
+    pub fn get(&self, url: &str) -> Option<StoredPage> {
+        self.pages.lock().unwrap().iter().find(|p| p.url == url).cloned()
+    }
+
+    pub fn list(&self) -> Vec<StoredPage> {
+        self.pages.lock().unwrap().iter().rev().cloned().collect()
+    }
+}
+
+pub fn resource_uri(url: &str) -> String {
+    format!("{RESOURCE_SCHEME}{url}")
+}
+
+/// Extracts the page URL from a `web://{url}` resource URI.
+pub fn url_from_uri(uri: &str) -> Option<&str> {
+    uri.strip_prefix(RESOURCE_SCHEME)
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(url: &str) -> StoredPage {
+        StoredPage {
+            url: url.to_string(),
+            title: None,
+            content_type: None,
+            mime_type: "text/plain",
+            text: String::new(),
+            final_url: url.to_string(),
+            redirects: Vec::new(),
+            status: 200,
+            elapsed_ms: 0,
+            from_cache: false,
+            fetched_at: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn insert_replaces_and_evicts_oldest() {
+        let store = PageStore::default();
+        for i in 0..MAX_PAGES {
+            store.insert(page(&format!("https://example.com/{i}")));
+        }
+        store.insert(page("https://example.com/0"));
+        store.insert(page("https://example.com/new"));
+        let list = store.list();
+        assert_eq!(list.len(), MAX_PAGES);
+        assert_eq!(list[0].url, "https://example.com/new");
+        assert!(store.get("https://example.com/1").is_none());
+        assert!(store.get("https://example.com/0").is_some());
+    }
+
+    #[test]
+    fn url_from_uri_requires_http_url() {
+        assert_eq!(url_from_uri("web://https://example.com/a"), Some("https://example.com/a"));
+        assert_eq!(url_from_uri("web://example.com"), None);
+        assert_eq!(url_from_uri("file:///etc/passwd"), None);
+    }
+}
@@ -0,0 +1,30 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+tokio::task_local! {
+    static CURRENT: Partial;
+}
+
+/// Holds the latest partial output of a tool call, so that a call cut short by its
+/// timeout can still return what it had so far. Recording is a no-op outside `tools/call`.
+#[derive(Clone, Default)]
+pub struct Partial {
+    latest: Arc<Mutex<Option<Value>>>,
+}
+
+impl Partial {
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self, fut).await
+    }
+
+    pub fn take(&self) -> Option<Value> {
+        self.latest.lock().unwrap().take()
+    }
+}
+
+/// Replaces the partial output recorded for the current tool call.
+pub fn record(value: Value) {
+    let _ = CURRENT.try_with(|p| *p.latest.lock().unwrap() = Some(value));
+}
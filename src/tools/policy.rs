@@ -3,12 +3,17 @@ use std::sync::Arc;
 use mcp_protocol_sdk::prelude::*;
 use url::Url;
 
-use crate::config::Config;
+use super::approval::{self, ApprovedDomains};
+use crate::config::{Config, PolicyMode};
 
 #[derive(Debug, Clone, Default)]
 pub struct DomainPolicy {
     allowed: Arc<Vec<String>>,
     blocked: Arc<Vec<String>>,
+    /// In `ask` mode, hosts outside the allow list (but not blocked) need user approval.
+    ask: bool,
+    /// Hosts the user chose to always allow in `ask` mode.
+    approved: Arc<ApprovedDomains>,
 }
 
 impl DomainPolicy {
@@ -29,7 +34,12 @@ impl DomainPolicy {
         allowed.dedup();
         blocked.sort();
         blocked.dedup();
-        Self { allowed: Arc::new(allowed), blocked: Arc::new(blocked) }
+        Self {
+            allowed: Arc::new(allowed),
+            blocked: Arc::new(blocked),
+            ask: cfg.policy_mode == PolicyMode::Ask,
+            approved: Arc::new(ApprovedDomains::from_config(cfg)),
+        }
     }
 
     pub fn allows_url(&self, url: &Url) -> bool {
@@ -47,7 +57,7 @@ impl DomainPolicy {
             return false;
         }
 
-        if self.allowed.is_empty() {
+        if self.allowed.is_empty() || (self.ask && self.approved.contains(&host)) {
             return true;
         }
 
@@ -58,6 +68,20 @@ impl DomainPolicy {
         patterns.iter().any(|pat| domain_matches(host, pat))
     }
 
+    /// Whether a host refused by `allows_host` may still be approved by the user.
+    pub fn can_ask(&self, host: &str) -> bool {
+        self.ask && !self.matches_any(&host.trim_end_matches('.').to_ascii_lowercase(), &self.blocked)
+    }
+
+    /// Where "always allow" answers are recorded.
+    pub fn approved(&self) -> &Arc<ApprovedDomains> {
+        &self.approved
+    }
+
+    pub fn allowed_domains(&self) -> &[String] {
+        &self.allowed
+    }
+
     pub fn is_empty(&self) -> bool {
         self.allowed.is_empty() && self.blocked.is_empty()
     }
@@ -73,7 +97,8 @@ impl DomainPolicy {
         } else {
             self.blocked.join(", ")
         };
-        format!("Allowed: {allowed}\nBlocked: {blocked}")
+        let ask = if self.ask { "\nOther domains require the user's approval." } else { "" };
+        format!("Allowed: {allowed}\nBlocked: {blocked}{ask}")
     }
 
     pub fn validation_error_message(&self, host: &str) -> String {
@@ -97,10 +122,52 @@ fn domain_matches(host: &str, pat: &str) -> bool {
     host == pat || host.ends_with(&format!(".{pat}"))
 }
 
-pub fn ensure_allowed(policy: &DomainPolicy, url: &Url) -> McpResult<()> {
-    if !policy.allows_url(url) {
-        let host = url.host_str().unwrap_or("");
-        return Err(McpError::validation(policy.validation_error_message(host)));
+/// Refuses URLs outside the domain policy. In `ask` mode the user is asked first
+/// (through the request's `Approver`) before an unlisted domain is refused.
+pub async fn ensure_allowed(policy: &DomainPolicy, url: &Url) -> McpResult<()> {
+    if policy.allows_url(url) {
+        return Ok(());
+    }
+    let host = url.host_str().unwrap_or("");
+    if policy.can_ask(host) {
+        if approval::approve(host).await {
+            tracing::info!(url = %url, host, "Domain approved by the user");
+            return Ok(());
+        }
+        tracing::info!(url = %url, host, "Domain not approved by the user");
+        return Err(McpError::validation(format!(
+            "Access to domain '{host}' was not approved by the user"
+        )));
+    }
+    tracing::info!(url = %url, host, "Denied by domain policy");
+    Err(McpError::validation(policy.validation_error_message(host)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn saved_approvals_allow_hosts_without_asking() {
+        let dir = std::env::temp_dir().join(format!("thomisidae-approvals-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("approved_domains.toml"), "domains = [\"docs.example.org\", \"bad.example.org\"]\n").unwrap();
+        let config = Config {
+            allowed_domains: vec!["example.com".into()],
+            blocked_domains: vec!["bad.example.org".into()],
+            policy_mode: PolicyMode::Ask,
+            persist_domain_approvals: true,
+            source: Some(dir.join("config.toml")),
+            ..Config::default()
+        };
+        let policy = DomainPolicy::from_config(&config);
+        // No approver is in scope, so anything that would need asking is refused.
+        assert!(ensure_allowed(&policy, &Url::parse("https://docs.example.org/a").unwrap()).await.is_ok());
+        assert!(ensure_allowed(&policy, &Url::parse("https://other.example.org/").unwrap()).await.is_err());
+        assert!(!policy.allows_host("bad.example.org"), "blocked wins over a saved approval");
+
+        let deny = DomainPolicy::from_config(&Config { policy_mode: PolicyMode::Deny, ..config });
+        assert!(!deny.allows_host("docs.example.org"));
+        let _ = std::fs::remove_dir_all(&dir);
     }
-    Ok(())
 }
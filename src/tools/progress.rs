@@ -0,0 +1,94 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{Value, json};
+
+tokio::task_local! {
+    static CURRENT: Progress;
+}
+
+type Notify = Arc<dyn Fn(Value) + Send + Sync>;
+
+/// Emits `notifications/progress` for the tool call that supplied a `progressToken`.
+/// Handlers reach it through the task-local set by `tools/call`, so reporting is a
+/// no-op when the client did not ask for progress.
+#[derive(Clone)]
+pub struct Progress {
+    token: Value,
+    notify: Notify,
+    last: Arc<Mutex<Option<f64>>>,
+}
+
+impl Progress {
+    pub fn new(token: Value, notify: impl Fn(Value) + Send + Sync + 'static) -> Self {
+        Self { token, notify: Arc::new(notify), last: Arc::new(Mutex::new(None)) }
+    }
+
+    pub fn current() -> Option<Progress> {
+        CURRENT.try_with(|p| p.clone()).ok()
+    }
+
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self, fut).await
+    }
+
+    /// Progress must increase with every notification; stale values are dropped.
+    pub fn report(&self, progress: f64, total: Option<f64>, message: impl Into<String>) {
+        {
+            let mut last = self.last.lock().unwrap();
+            if last.is_some_and(|l| progress <= l) {
+                return;
+            }
+            *last = Some(progress);
+        }
+        let mut params = json!({
+            "progressToken": self.token,
+            "progress": progress,
+            "message": message.into(),
+        });
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        (self.notify)(params);
+    }
+
+    /// Reports a new phase one unit past the last reported value.
+    pub fn step(&self, message: impl Into<String>) {
+        let next = self.last.lock().unwrap().map_or(0.0, |l| l + 1.0);
+        self.report(next, None, message);
+    }
+}
+
+pub fn report(progress: f64, total: Option<f64>, message: impl Into<String>) {
+    if let Some(p) = Progress::current() {
+        p.report(progress, total, message);
+    }
+}
+
+pub fn step(message: impl Into<String>) {
+    if let Some(p) = Progress::current() {
+        p.step(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_skips_non_increasing_values() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sink = sent.clone();
+        let p = Progress::new(json!("tok"), move |v| sink.lock().unwrap().push(v));
+        p.report(0.0, None, "start");
+        p.report(10.0, Some(20.0), "half");
+        p.report(10.0, Some(20.0), "again");
+        p.report(20.0, Some(20.0), "done");
+        p.step("extract");
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 4);
+        assert_eq!(sent[3]["progress"], json!(21.0));
+        assert_eq!(sent[1]["total"], json!(20.0));
+        assert_eq!(sent[2]["progressToken"], json!("tok"));
+    }
+}
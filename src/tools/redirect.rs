@@ -0,0 +1,171 @@
+use mcp_protocol_sdk::prelude::*;
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::{Response, StatusCode};
+use tracing::{debug, info};
+use url::Url;
+
+use super::net::HttpClient;
+use super::policy::{DomainPolicy, ensure_allowed};
+use super::robots::Robots;
+use super::utils::{ensure_fetchable, request_error};
+
+/// Most redirects followed for one request.
+pub const MAX_REDIRECTS: usize = 10;
+
+/// What a redirect target has to pass before it is requested. The shared client does
+/// not follow redirects itself, so a URL that passed the domain policy and robots.txt
+/// cannot lead somewhere they would refuse.
+#[derive(Clone, Copy, Default)]
+pub struct HopCheck<'a> {
+    pub policy: Option<&'a DomainPolicy>,
+    /// `None` when robots.txt is not obeyed for this request.
+    pub robots: Option<&'a Robots>,
+}
+
+impl HopCheck<'_> {
+    /// Checks one redirect target.
+    pub async fn check(&self, url: &Url) -> McpResult<()> {
+        let refused = |reason: String| McpError::validation(format!("Redirect to {url} refused: {reason}"));
+        if let Some(policy) = self.policy {
+            ensure_allowed(policy, url).await.map_err(|e| match e {
+                McpError::Validation(msg) => refused(msg),
+                other => other,
+            })?;
+        }
+        // Boxed because fetching robots.txt goes through `get` as well.
+        if let Some(robots) = self.robots
+            && !Box::pin(robots.allow(url)).await?
+        {
+            info!(url = %url, "Redirect blocked by robots.txt");
+            return Err(refused("blocked by robots.txt".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// The final response of a request, with the URLs that redirected to it.
+pub struct Followed {
+    pub response: Response,
+    pub url: Url,
+    /// Every URL that answered with a redirect, starting with the requested one.
+    pub redirects: Vec<Url>,
+}
+
+/// GETs `url`, following up to `MAX_REDIRECTS` redirects. Every hop is checked like the
+/// first request (http(s) only, no literal IPs the client's filter refuses) and against `hops`.
+pub async fn get(client: &HttpClient, url: &str, headers: HeaderMap, hops: HopCheck<'_>) -> McpResult<Followed> {
+    ensure_fetchable(url, &client.ips)?;
+    let mut current = Url::parse(url).map_err(|e| McpError::validation(format!("Invalid url: {e}")))?;
+    let mut redirects = Vec::new();
+    loop {
+        let response = client
+            .client
+            .get(current.clone())
+            .headers(headers.clone())
+            .send()
+            .await
+            .map_err(request_error)?;
+        let Some(next) = redirect_target(&current, response.status(), response.headers())? else {
+            return Ok(Followed { response, url: current, redirects });
+        };
+        if redirects.len() >= MAX_REDIRECTS {
+            return Err(McpError::validation(format!("Too many redirects (more than {MAX_REDIRECTS})")));
+        }
+        debug!(from = %current, to = %next, status = response.status().as_u16(), "Following redirect");
+        ensure_fetchable(next.as_str(), &client.ips)?;
+        hops.check(&next).await?;
+        redirects.push(std::mem::replace(&mut current, next));
+    }
+}
+
+/// Where a redirect response points, resolved against the URL that returned it.
+fn redirect_target(current: &Url, status: StatusCode, headers: &HeaderMap) -> McpResult<Option<Url>> {
+    if !status.is_redirection() || matches!(status, StatusCode::NOT_MODIFIED | StatusCode::MULTIPLE_CHOICES) {
+        return Ok(None);
+    }
+    let Some(location) = headers.get(LOCATION) else {
+        return Ok(None);
+    };
+    location
+        .to_str()
+        .ok()
+        .and_then(|l| current.join(l).ok())
+        .map(Some)
+        .ok_or_else(|| McpError::validation(format!("Invalid redirect location from {current}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use reqwest::header::HeaderValue;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn target(status: u16, location: Option<&str>) -> McpResult<Option<String>> {
+        let current = Url::parse("https://example.com/docs/page?x=1").unwrap();
+        let mut headers = HeaderMap::new();
+        if let Some(location) = location {
+            headers.insert(LOCATION, HeaderValue::from_str(location).unwrap());
+        }
+        redirect_target(&current, StatusCode::from_u16(status).unwrap(), &headers).map(|u| u.map(String::from))
+    }
+
+    #[test]
+    fn redirect_target_resolves_location() {
+        assert_eq!(target(301, Some("/other")).unwrap().as_deref(), Some("https://example.com/other"));
+        assert_eq!(target(302, Some("next#top")).unwrap().as_deref(), Some("https://example.com/docs/next#top"));
+        assert_eq!(target(307, Some("//cdn.example.net/a")).unwrap().as_deref(), Some("https://cdn.example.net/a"));
+        assert_eq!(target(308, Some("http://elsewhere.test/")).unwrap().as_deref(), Some("http://elsewhere.test/"));
+        assert_eq!(target(303, None).unwrap(), None);
+        assert_eq!(target(304, Some("/other")).unwrap(), None);
+        assert_eq!(target(300, Some("/other")).unwrap(), None);
+        assert_eq!(target(200, Some("/other")).unwrap(), None);
+        assert!(target(302, Some("http://[::1")).is_err());
+    }
+
+    #[tokio::test]
+    async fn hop_check_refuses_blocked_domains() {
+        let config = Config { blocked_domains: vec!["blocked.test".into()], ..Config::default() };
+        let policy = DomainPolicy::from_config(&config);
+        let hops = HopCheck { policy: Some(&policy), robots: None };
+        assert!(hops.check(&Url::parse("https://fine.test/").unwrap()).await.is_ok());
+        let err = hops.check(&Url::parse("https://www.blocked.test/x").unwrap()).await.unwrap_err();
+        assert!(err.to_string().contains("Redirect to https://www.blocked.test/x refused"), "{err}");
+        assert!(HopCheck::default().check(&Url::parse("https://www.blocked.test/").unwrap()).await.is_ok());
+    }
+
+    /// Serves `302 Location: <location>` to every request.
+    async fn redirecting_server(location: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 1024];
+                let _ = socket.read(&mut buf).await;
+                let reply = format!("HTTP/1.1 302 Found\r\nLocation: {location}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                let _ = socket.write_all(reply.as_bytes()).await;
+            }
+        });
+        format!("http://localhost:{port}/start")
+    }
+
+    #[tokio::test]
+    async fn get_stops_at_unsafe_hops_and_loops() {
+        let client = HttpClient {
+            client: reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap(),
+            ips: Default::default(),
+        };
+
+        let url = redirecting_server("/again").await;
+        let err = get(&client, &url, HeaderMap::new(), HopCheck::default()).await.err().unwrap();
+        assert!(err.to_string().contains("Too many redirects"), "{err}");
+
+        let url = redirecting_server("http://169.254.169.254/latest/meta-data/").await;
+        let err = get(&client, &url, HeaderMap::new(), HopCheck::default()).await.err().unwrap();
+        assert!(err.to_string().contains("non-global IP"), "{err}");
+
+        let url = redirecting_server("file:///etc/passwd").await;
+        let err = get(&client, &url, HeaderMap::new(), HopCheck::default()).await.err().unwrap();
+        assert!(err.to_string().contains("Unsupported URL scheme"), "{err}");
+    }
+}
@@ -2,14 +2,18 @@ use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 use mcp_protocol_sdk::prelude::*;
-use reqwest::Client;
+use reqwest::header::HeaderMap;
 use robotstxt::DefaultMatcher;
 use tokio::sync::RwLock;
+use tracing::debug;
 use url::Url;
 
+use super::net::HttpClient;
+use super::redirect::{self, HopCheck};
+
 #[derive(Debug)]
 pub struct Robots {
-    client: Client,
+    client: HttpClient,
     user_agent: String,
     obey: bool,
     ttl: Duration,
@@ -23,7 +27,7 @@ struct CacheEntry {
 }
 
 impl Robots {
-    pub fn new(client: Client, user_agent: String, obey: bool, ttl_secs: u64) -> Self {
+    pub fn new(client: HttpClient, user_agent: String, obey: bool, ttl_secs: u64) -> Self {
         Self {
             client,
             user_agent,
@@ -46,12 +50,20 @@ impl Robots {
             let cache = self.cache.read().await;
             if let Some(entry) = cache.get(&origin)
                 && entry.fresh(self.ttl) {
-                return Ok(DefaultMatcher::default()
-                    .one_agent_allowed_by_robots(&entry.body, &self.user_agent, url.as_str()));
+                let allowed = DefaultMatcher::default()
+                    .one_agent_allowed_by_robots(&entry.body, &self.user_agent, url.as_str());
+                debug!(url = %url, allowed, "robots.txt decision (cached)");
+                return Ok(allowed);
             }
         }
 
-        let body = self.fetch_robots_body_for(&origin).await.unwrap_or_default();
+        let body = match self.fetch_robots_body_for(&origin).await {
+            Ok(body) => body,
+            Err(e) => {
+                debug!(origin = %origin, error = %e, "robots.txt fetch failed; treating as allow-all");
+                String::new()
+            }
+        };
 
         {
             let mut cache = self.cache.write().await;
@@ -62,14 +74,19 @@ impl Robots {
         }
 
         if body.is_empty() {
+            debug!(origin = %origin, "No robots.txt rules; allowing");
             return Ok(true);
         }
-        Ok(DefaultMatcher::default().one_agent_allowed_by_robots(&body, &self.user_agent, url.as_str()))
+        let allowed = DefaultMatcher::default().one_agent_allowed_by_robots(&body, &self.user_agent, url.as_str());
+        debug!(url = %url, allowed, "robots.txt decision");
+        Ok(allowed)
     }
 
     async fn fetch_robots_body_for(&self, origin: &str) -> McpResult<String> {
         let robots_url = format!("{origin}/robots.txt");
-        let resp = self.client.get(&robots_url).send().await.map_err(|e| McpError::internal(e.to_string()))?;
+        // Redirects (often http -> https or to a canonical host) are followed without
+        // the domain policy: reading robots.txt is not fetching content.
+        let resp = redirect::get(&self.client, &robots_url, HeaderMap::new(), HopCheck::default()).await?.response;
         if !resp.status().is_success() {
             return Ok(String::new());
         }
@@ -0,0 +1,52 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use serde_json::{Value, json};
+
+tokio::task_local! {
+    static CURRENT: Sampler;
+}
+
+type CreateMessage = Arc<dyn Fn(Value) -> BoxFuture<'static, Result<Value, String>> + Send + Sync>;
+
+/// Sends `sampling/createMessage` to the client, letting a tool use the client's model.
+/// Set by the server for requests from clients that advertise the sampling capability.
+#[derive(Clone)]
+pub struct Sampler {
+    create_message: CreateMessage,
+}
+
+impl Sampler {
+    pub fn new<F, Fut>(create_message: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        Self { create_message: Arc::new(move |params| Box::pin(create_message(params))) }
+    }
+
+    pub fn current() -> Option<Sampler> {
+        CURRENT.try_with(|s| s.clone()).ok()
+    }
+
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self, fut).await
+    }
+
+    /// Asks the client's model to answer a single user message and returns its text.
+    pub async fn complete(&self, system_prompt: &str, prompt: String, max_tokens: u32) -> Result<String, String> {
+        let params = json!({
+            "messages": [{ "role": "user", "content": { "type": "text", "text": prompt } }],
+            "systemPrompt": system_prompt,
+            "includeContext": "none",
+            "maxTokens": max_tokens,
+        });
+        let result = (self.create_message)(params).await?;
+        result
+            .pointer("/content/text")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| "The client's reply to sampling/createMessage had no text content".to_string())
+    }
+}
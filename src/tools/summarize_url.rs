@@ -0,0 +1,228 @@
+use async_trait::async_trait;
+use mcp_protocol_sdk::prelude::*;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use super::cache::cache_arg_schema;
+use super::fetch_text::FetchTextHandler;
+use super::fetch_text::chunk::chunk;
+use super::fetch_text::handler::FetchTextOptions;
+use super::meta::{ToolAnnotations, ToolInputSchema, ToolMeta};
+use super::partial;
+use super::progress;
+use super::sampling::Sampler;
+use super::utils::{execution_result, structured_tool_result, tool_error_result};
+
+/// Size of each piece of page text sent to the client's model.
+const PIECE_LEN: usize = 12_000;
+/// Pages longer than this many pieces are summarized up to that point only.
+const MAX_PIECES: usize = 16;
+const PIECE_MAX_TOKENS: u32 = 600;
+const MERGE_MAX_TOKENS: u32 = 1500;
+
+const SYSTEM_PROMPT: &str = "You summarize web pages faithfully. Only use information from the provided text.";
+
+static META: OnceLock<ToolMeta> = OnceLock::new();
+
+pub fn meta() -> ToolMeta {
+    META.get_or_init(|| {
+        let properties = json!({
+            "url": { "type": "string" },
+            "focus": { "type": "string", "description": "Optional aspect the summary should concentrate on." },
+            "mode": { "type": "string", "enum": ["auto", "best_blocks", "readability"], "default": "auto" },
+            "respect_robots": { "type": ["boolean", "null"], "description": "Override robots behavior for this call" },
+            "allow_error_status": { "type": "boolean", "default": false, "description": "Summarize the page even when the server answers with a non-2xx status" },
+            "cache": cache_arg_schema()
+        });
+        let schema = ToolInputSchema::new("object", properties, vec!["url".to_string()]);
+        ToolMeta::new(
+            "summarize_url",
+            "Summarize URL",
+            "Fetches a page and summarizes all of it with the client's model (MCP sampling), citing character offsets into the extracted text. Only offered to clients that support sampling.",
+            schema,
+        )
+        .with_output_schema(json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "status": { "type": "integer", "description": "HTTP status of the final response" },
+                "title": { "type": ["string", "null"] },
+                "summary": { "type": "string" },
+                "sections": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "start_index": { "type": "integer" },
+                            "end_index": { "type": "integer" },
+                            "summary": { "type": "string" }
+                        },
+                        "required": ["start_index", "end_index", "summary"]
+                    }
+                },
+                "total_length": { "type": "integer" },
+                "truncated": { "type": "boolean", "description": "True when only the beginning of a very long page was summarized" }
+            },
+            "required": ["url", "status", "summary", "sections", "total_length", "truncated"]
+        }))
+        .with_annotations(ToolAnnotations::read_only_web())
+    })
+    .clone()
+}
+
+pub struct SummarizeUrlHandler {
+    pub fetch_text: Arc<FetchTextHandler>,
+}
+
+#[async_trait]
+impl ToolHandler for SummarizeUrlHandler {
+    async fn call(&self, arguments: HashMap<String, Value>) -> McpResult<ToolResult> {
+        let opts = FetchTextOptions::from_arguments(&arguments)?;
+        let focus = arguments
+            .get("focus")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        let Some(sampler) = Sampler::current() else {
+            return Ok(tool_error_result(
+                "summarize_url needs a client that supports sampling; use fetch_url_text instead.",
+            ));
+        };
+        execution_result(self.run(&sampler, opts, focus.as_deref()).await)
+    }
+}
+
+struct Section {
+    start: usize,
+    end: usize,
+    summary: String,
+}
+
+struct Summary {
+    text: String,
+    sections: Vec<Section>,
+    /// Characters covered by the sections; less than the page length when truncated.
+    covered: usize,
+}
+
+impl SummarizeUrlHandler {
+    async fn run(&self, sampler: &Sampler, opts: FetchTextOptions, focus: Option<&str>) -> McpResult<ToolResult> {
+        let page = self.fetch_text.extract(opts).await?;
+        let total = page.text.len();
+        let Summary { text: summary, sections, covered } = summarize(sampler, &page.url, &page.text, focus)
+            .await
+            .map_err(|e| McpError::internal(format!("Sampling failed: {e}")))?;
+        let truncated = covered < total;
+
+        let mut text = match &page.title {
+            Some(title) => format!("Summary of {title}\nURL: {}\n\n{summary}", page.url),
+            None => format!("Summary of {}\n\n{summary}", page.url),
+        };
+        if truncated {
+            text.push_str(&format!(
+                "\n\n(Only the first {covered} of {total} characters were summarized.)"
+            ));
+        }
+        Ok(structured_tool_result(
+            text,
+            json!({
+                "url": page.url,
+                "status": page.status,
+                "title": page.title,
+                "summary": summary,
+                "sections": sections_json(&sections),
+                "total_length": total,
+                "truncated": truncated,
+            }),
+        ))
+    }
+}
+
+/// Summarizes `text` piece by piece with the client's model, then merges the partial
+/// summaries into one that cites the character range of each piece.
+async fn summarize(sampler: &Sampler, url: &str, text: &str, focus: Option<&str>) -> Result<Summary, String> {
+    let total = text.len();
+    let focus_note = focus.map(|f| format!(" Focus on: {f}.")).unwrap_or_default();
+
+    let mut pieces = Vec::new();
+    let mut covered = 0;
+    while pieces.len() < MAX_PIECES
+        && let Some(piece) = chunk(text, covered, PIECE_LEN)
+    {
+        covered = piece.end;
+        pieces.push(piece);
+    }
+
+    let steps = pieces.len() as f64 + 1.0;
+    let mut sections = Vec::with_capacity(pieces.len());
+    for (i, piece) in pieces.iter().enumerate() {
+        progress::report(i as f64, Some(steps), format!("Summarizing part {} of {}", i + 1, pieces.len()));
+        let prompt = format!(
+            "Summarize part {} of {} of the page {url} (characters {}-{} of {total}).{focus_note} Keep key facts, names and numbers. Reply with the summary only.\n\n---\n{}",
+            i + 1,
+            pieces.len(),
+            piece.start,
+            piece.end,
+            piece.text
+        );
+        let summary = sampler.complete(SYSTEM_PROMPT, prompt, PIECE_MAX_TOKENS).await?;
+        sections.push(Section { start: piece.start, end: piece.end, summary });
+        partial::record(json!({ "url": url, "sections": sections_json(&sections), "total_length": total }));
+    }
+
+    let text = match sections.as_slice() {
+        [] => String::new(),
+        [only] => only.summary.clone(),
+        _ => {
+            progress::report(sections.len() as f64, Some(steps), "Merging summaries");
+            let parts: Vec<String> = sections
+                .iter()
+                .map(|s| format!("[{}-{}]\n{}", s.start, s.end, s.summary))
+                .collect();
+            let prompt = format!(
+                "Below are summaries of consecutive parts of {url}, each labelled with its character range.{focus_note} Merge them into one coherent summary. After each statement, cite the range it comes from, e.g. [0-12000]. Reply with the summary only.\n\n{}",
+                parts.join("\n\n")
+            );
+            sampler.complete(SYSTEM_PROMPT, prompt, MERGE_MAX_TOKENS).await?
+        }
+    };
+    Ok(Summary { text, sections, covered })
+}
+
+fn sections_json(sections: &[Section]) -> Vec<Value> {
+    sections
+        .iter()
+        .map(|s| json!({ "start_index": s.start, "end_index": s.end, "summary": s.summary }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn summarize_splits_then_merges() {
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+        let seen = prompts.clone();
+        let sampler = Sampler::new(move |params: Value| {
+            let n = {
+                let mut seen = seen.lock().unwrap();
+                seen.push(params);
+                seen.len()
+            };
+            async move { Ok(json!({ "role": "assistant", "content": { "type": "text", "text": format!("s{n}") } })) }
+        });
+
+        let text = "a".repeat(PIECE_LEN * 2 + 10);
+        let summary = summarize(&sampler, "https://example.com/", &text, None).await.unwrap();
+        assert_eq!(summary.sections.len(), 3);
+        assert_eq!(summary.sections[2].start, PIECE_LEN * 2);
+        assert_eq!(summary.covered, text.len());
+        assert_eq!(summary.text, "s4");
+        let merge = prompts.lock().unwrap()[3]["messages"][0]["content"]["text"].as_str().unwrap().to_string();
+        assert!(merge.contains(&format!("[{}-{}]\ns2", PIECE_LEN, PIECE_LEN * 2)));
+    }
+}
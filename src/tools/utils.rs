@@ -1,13 +1,13 @@
 use mcp_protocol_sdk::prelude::*;
-use reqwest::Client;
-use reqwest::header::CONTENT_TYPE;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::IpAddr;
 use url::Url;
-use futures_util::StreamExt;
 use encoding_rs::Encoding;
 use chardetng::EncodingDetector;
+use tracing::warn;
+
+use super::net::IpFilter;
 
 
 pub fn required_str_arg(
@@ -22,156 +22,48 @@ pub fn required_str_arg(
 }
 
 
-pub async fn fetch_html(client: &Client, url: &str, max_response_size: usize) -> McpResult<String> {
+/// Only http(s) URLs are fetched. Hostnames are checked when the client resolves them
+/// (`net::FilteringResolver`); literal IPs never reach the resolver, so they are checked
+/// here, for the requested URL and every redirect target.
+pub(crate) fn ensure_fetchable(url: &str, ips: &IpFilter) -> McpResult<()> {
     let parsed = Url::parse(url).map_err(|e| McpError::validation(format!("Invalid url: {e}")))?;
     if !matches!(parsed.scheme(), "http" | "https") {
         return Err(McpError::validation(format!(
             "Unsupported URL scheme: {} (only http/https allowed)", parsed.scheme()
         )));
     }
-    
-    if let Some(host) = parsed.host_str()
-        && let Ok(ip) = host.parse::<IpAddr>()
-        && !is_global_ip(ip)
-    {
-        return Err(McpError::validation("URL host resolves to a non-global IP (blocked)".to_string()));
-    }
 
-    let resp = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| McpError::internal(e.to_string()))?;
-
-
-    if let Some(len) = resp.content_length()
-        && (len as usize > max_response_size)
-    {
-        return Err(McpError::validation(format!(
-            "Response too large: {len} bytes (max {max_response_size})"
-        )));
-    }
-
-
-    let mut total: usize = 0;
-    let mut out = Vec::with_capacity(64 * 1024);
-    let mut stream = resp.bytes_stream();
-    while let Some(chunk_res) = stream.next().await {
-        let chunk = chunk_res.map_err(|e| McpError::internal(e.to_string()))?;
-        total = total.saturating_add(chunk.len());
-        if total > max_response_size {
-            return Err(McpError::validation(format!(
-                "Response exceeded limit ({max_response_size} bytes)"
-            )));
-        }
-        out.extend_from_slice(&chunk);
-    }
-
-    let text = String::from_utf8_lossy(&out).into_owned();
-    Ok(text)
-}
-
-#[derive(Debug, Clone)]
-pub struct FetchedResponse {
-    pub body: String,
-    pub content_type: Option<String>,
-}
-
-pub async fn fetch_html_with_headers(client: &Client, url: &str, max_response_size: usize) -> McpResult<FetchedResponse> {
-    let parsed = Url::parse(url).map_err(|e| McpError::validation(format!("Invalid url: {e}")))?;
-    if !matches!(parsed.scheme(), "http" | "https") {
-        return Err(McpError::validation(format!(
-            "Unsupported URL scheme: {} (only http/https allowed)", parsed.scheme()
-        )));
-    }
-
-    if let Some(host) = parsed.host_str()
-        && let Ok(ip) = host.parse::<IpAddr>()
-        && !is_global_ip(ip)
+    if let Some(ip) = literal_ip(&parsed)
+        && !ips.allows(ip)
     {
+        warn!(url, %ip, "Blocked fetch to non-global IP");
         return Err(McpError::validation("URL host resolves to a non-global IP (blocked)".to_string()));
     }
-
-    let resp = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| McpError::internal(e.to_string()))?;
-
-    if let Some(len) = resp.content_length()
-        && (len as usize > max_response_size)
-    {
-        return Err(McpError::validation(format!(
-            "Response too large: {len} bytes (max {max_response_size})"
-        )));
-    }
-
-    let ct = resp
-        .headers()
-        .get(CONTENT_TYPE)
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string());
-
-    let mut total: usize = 0;
-    let mut out = Vec::with_capacity(64 * 1024);
-    let mut stream = resp.bytes_stream();
-    while let Some(chunk_res) = stream.next().await {
-        let chunk = chunk_res.map_err(|e| McpError::internal(e.to_string()))?;
-        total = total.saturating_add(chunk.len());
-        if total > max_response_size {
-            return Err(McpError::validation(format!(
-                "Response exceeded limit ({max_response_size} bytes)"
-            )));
-        }
-        out.extend_from_slice(&chunk);
-    }
-
-    let body = decode_body(&out, ct.as_deref());
-    Ok(FetchedResponse { body, content_type: ct })
+    Ok(())
 }
 
-fn is_global_ip(ip: IpAddr) -> bool {
-    match ip {
-        IpAddr::V4(v4) => is_global_ipv4(v4),
-        IpAddr::V6(v6) => is_global_ipv6(v6),
+/// reqwest's own message ("error sending request for url ...") hides the cause, such as
+/// a DNS failure or an address refused by the network filter, so the causes are appended.
+pub fn request_error(e: reqwest::Error) -> McpError {
+    let mut message = e.to_string();
+    let mut source = std::error::Error::source(&e);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
     }
+    McpError::internal(message)
 }
 
-fn is_global_ipv4(ip: Ipv4Addr) -> bool {
-    let octets = ip.octets();
-    // 10.0.0.0/8
-    if octets[0] == 10 { return false; }
-    // 172.16.0.0/12
-    if octets[0] == 172 && (16..=31).contains(&octets[1]) { return false; }
-    // 192.168.0.0/16
-    if octets[0] == 192 && octets[1] == 168 { return false; }
-    // 127.0.0.0/8 loopback
-    if octets[0] == 127 { return false; }
-    // 169.254.0.0/16 link-local
-    if octets[0] == 169 && octets[1] == 254 { return false; }
-    // 0.0.0.0/8, 255.255.255.255 broadcast
-    if octets[0] == 0 || ip == Ipv4Addr::new(255,255,255,255) { return false; }
-    // 224.0.0.0/4 multicast & 240.0.0.0/4 reserved
-    if (224..=255).contains(&octets[0]) { return false; }
-    true
-}
-
-fn is_global_ipv6(ip: Ipv6Addr) -> bool {
-    let seg0 = ip.segments()[0];
-    // ::/128 unspecified
-    if ip.is_unspecified() { return false; }
-    // ::1/128 loopback
-    if ip.is_loopback() { return false; }
-    // fe80::/10 link-local
-    if (seg0 & 0xffc0) == 0xfe80 { return false; }
-    // fc00::/7 unique local
-    if (seg0 & 0xfe00) == 0xfc00 { return false; }
-    // ff00::/8 multicast
-    if (seg0 & 0xff00) == 0xff00 { return false; }
-    true
+/// The host of `url` when it is an IP address rather than a name.
+pub fn literal_ip(url: &Url) -> Option<IpAddr> {
+    match url.host()? {
+        url::Host::Ipv4(v4) => Some(IpAddr::V4(v4)),
+        url::Host::Ipv6(v6) => Some(IpAddr::V6(v6)),
+        url::Host::Domain(_) => None,
+    }
 }
 
-
 pub fn text_tool_result<T: Into<String>>(text: T) -> ToolResult {
     ToolResult {
         content: vec![Content::Text {
@@ -185,6 +77,44 @@ pub fn text_tool_result<T: Into<String>>(text: T) -> ToolResult {
     }
 }
 
+/// Result carrying `structuredContent` (matching the tool's `outputSchema`) plus a
+/// text rendering for clients that do not read structured output.
+pub fn structured_tool_result<T: Into<String>>(text: T, structured: Value) -> ToolResult {
+    ToolResult {
+        structured_content: Some(structured),
+        ..text_tool_result(text)
+    }
+}
+
+pub fn tool_error_result<T: Into<String>>(text: T) -> ToolResult {
+    ToolResult {
+        is_error: Some(true),
+        ..text_tool_result(text)
+    }
+}
+
+/// Handlers report missing or malformed arguments as `Err` (a JSON-RPC protocol error).
+/// Failures while running the tool (policy denials, robots.txt blocks, network and
+/// HTTP errors) go through here and come back as `isError` results the model can read.
+/// Cancellation and timeouts stay protocol errors.
+pub fn execution_result(result: McpResult<ToolResult>) -> McpResult<ToolResult> {
+    match result {
+        Ok(tr) => Ok(tr),
+        Err(e @ (McpError::Cancelled(_) | McpError::Timeout(_))) => Err(e),
+        Err(e) => Ok(tool_error_result(error_message(e))),
+    }
+}
+
+fn error_message(e: McpError) -> String {
+    match e {
+        McpError::Validation(msg)
+        | McpError::Internal(msg)
+        | McpError::Url(msg)
+        | McpError::Io(msg) => msg,
+        other => other.to_string(),
+    }
+}
+
 /// Decode HTTP response body using charset from Content-Type if available;
 /// otherwise use chardetng to guess. Falls back to UTF-8 lossless.
 pub fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
@@ -212,3 +142,18 @@ fn parse_charset(content_type: &str) -> Option<String> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execution_result_maps_failures_to_is_error() {
+        let blocked = execution_result(Err(McpError::validation("Blocked by robots.txt"))).unwrap();
+        assert_eq!(blocked.is_error, Some(true));
+        assert!(matches!(&blocked.content[0], Content::Text { text, .. } if text == "Blocked by robots.txt"));
+
+        let cancelled = execution_result(Err(McpError::Cancelled("stop".into())));
+        assert!(matches!(cancelled, Err(McpError::Cancelled(_))));
+    }
+}